@@ -0,0 +1,194 @@
+use crate::config::{Config, DEFAULT_CONFIG};
+use std::path::{Path, PathBuf};
+
+
+pub struct Args {
+    pub headless: bool,
+    pub frames: usize,
+    pub screenshot: PathBuf,
+    pub ram_dump: Option<PathBuf>,
+    pub audio: Option<PathBuf>,
+    pub log_illegal_writes: bool,
+    pub record_movie: Option<PathBuf>,
+    pub play_movie: Option<PathBuf>,
+    pub debug: bool,
+    /// --help was given, nothing else is parsed
+    pub help: bool,
+    /// The config file with the command line options applied on top
+    pub config: Config,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            headless: false,
+            frames: 60,
+            screenshot: PathBuf::from("frame.ppm"),
+            ram_dump: None,
+            audio: None,
+            log_illegal_writes: false,
+            record_movie: None,
+            play_movie: None,
+            debug: false,
+            help: false,
+            config: Config::default(),
+        }
+    }
+}
+
+pub const USAGE: &str = "\
+Usage: space_invaders_emulator [OPTIONS]
+
+Options:
+    --config PATH       Read settings from a TOML file (default: ./invaders.toml if it exists),
+                        options given on the command line take precedence
+    --rom-dir PATH      invaders.zip, or a directory with invaders.zip or
+                        invaders.h, .g, .f and .e (default: ./rom)
+    --sound-dir DIR     Directory with the sound samples (default: ./sounds)
+    --scale N           Window size as a multiple of 224x256
+    --lives N           Start lives, 3 to 6 (default: 3)
+    --extra-ship        Extra ship at 1000 points instead of 1500
+    --muted             Start with sound muted
+    --synth             Synthesize all sounds instead of playing samples
+    --cocktail          Cocktail table: turn the screen around for the second player
+    --bind BUTTON=KEY   Bind a cabinet button to an SDL key name, e.g. fire=Up;
+                        buttons: p1_start, p2_start, fire, left, right, coin, tilt
+    --headless          Run without a window or sound device
+    --frames N          Number of frames to run in headless mode (default: 60)
+    --screenshot PATH   Where to write the final frame as PPM (default: frame.ppm)
+    --ram-dump PATH     Also write RAM 0x2000..0x4000 to PATH
+    --audio PATH        Also render the sound of the run to a WAV file
+    --log-illegal-writes
+                        Report writes to ROM and unmapped addresses
+    --record-movie PATH Record the inputs of every frame from power-on, saved to PATH on exit
+    --play-movie PATH   Replay a recorded movie instead of reading the keyboard;
+                        in headless mode runs the whole movie and ignores --frames
+    --debug             Start paused in the debugger, see `help` at its prompt
+    --help              Print this message";
+
+impl Args {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut config_path = None;
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("{} expects a value", name));
+
+            match arg.as_str() {
+                "--config" => config_path = Some(PathBuf::from(value("--config")?)),
+                "--rom-dir" => parsed.config.rom_dir = Some(PathBuf::from(value("--rom-dir")?)),
+                "--sound-dir" => parsed.config.sound_dir = Some(PathBuf::from(value("--sound-dir")?)),
+                "--scale" => {
+                    let scale = value("--scale")?;
+                    parsed.config.scale = Some(scale.parse().map_err(|_| format!("invalid window scale: {}", scale))?);
+                }
+                "--lives" => {
+                    let lives = value("--lives")?;
+                    parsed.config.lives = Some(lives.parse().map_err(|_| format!("invalid number of lives: {}", lives))?);
+                }
+                "--extra-ship" => parsed.config.extra_ship = Some(true),
+                "--muted" => parsed.config.muted = Some(true),
+                "--synth" => parsed.config.synth = Some(true),
+                "--cocktail" => parsed.config.cocktail = Some(true),
+                "--bind" => {
+                    let binding = value("--bind")?;
+                    let (button, key) = binding.split_once('=').ok_or(format!("expected BUTTON=KEY, got {}", binding))?;
+                    parsed.config.keys.insert(button.to_string(), key.to_string());
+                }
+                "--headless" => parsed.headless = true,
+                "--frames" => {
+                    let frames = value("--frames")?;
+                    parsed.frames = frames.parse().map_err(|_| format!("invalid frame count: {}", frames))?;
+                }
+                "--screenshot" => parsed.screenshot = PathBuf::from(value("--screenshot")?),
+                "--ram-dump" => parsed.ram_dump = Some(PathBuf::from(value("--ram-dump")?)),
+                "--audio" => parsed.audio = Some(PathBuf::from(value("--audio")?)),
+                "--log-illegal-writes" => parsed.log_illegal_writes = true,
+                "--record-movie" => parsed.record_movie = Some(PathBuf::from(value("--record-movie")?)),
+                "--play-movie" => parsed.play_movie = Some(PathBuf::from(value("--play-movie")?)),
+                "--debug" => parsed.debug = true,
+                "--help" | "-h" => {
+                    parsed.help = true;
+                    return Ok(parsed);
+                }
+                _ => return Err(format!("unknown argument: {}\n\n{}", arg, USAGE)),
+            }
+        }
+
+        if parsed.record_movie.is_some() && parsed.play_movie.is_some() {
+            return Err("--record-movie and --play-movie cannot be used together".to_string());
+        }
+
+        let file_config = match config_path {
+            Some(path) => Config::load(&path)?,
+            None if Path::new(DEFAULT_CONFIG).exists() => Config::load(Path::new(DEFAULT_CONFIG))?,
+            None => Config::default(),
+        };
+        parsed.config = file_config.merge(parsed.config);
+        parsed.config.validate()?;
+
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // parses `args` with `config` as the config file, so no ./invaders.toml gets in the way
+    fn parse_with_config(config: &str, args: &[&str]) -> Result<Args, String> {
+        let path = std::env::temp_dir().join(format!("args-{}-{:x}.toml", std::process::id(), crc32fast::hash(format!("{}{:?}", config, args).as_bytes())));
+        std::fs::write(&path, config).unwrap();
+        let mut all = vec!["--config".to_string(), path.display().to_string()];
+        all.extend(args.iter().map(|arg| arg.to_string()));
+        let parsed = Args::parse(all.into_iter());
+        std::fs::remove_file(&path).unwrap();
+        parsed
+    }
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_with_config("", args)
+    }
+
+    #[test]
+    fn help_is_not_an_error() {
+        assert!(parse(&["--help"]).unwrap().help);
+        assert!(parse(&["--lives", "9", "-h"]).unwrap().help);
+        assert!(!parse(&[]).unwrap().help);
+    }
+
+    #[test]
+    fn flags_and_values_are_parsed() {
+        let args = parse(&["--headless", "--frames", "120", "--lives", "5", "--bind", "fire=Up", "--audio", "run.wav"]).unwrap();
+        assert!(args.headless);
+        assert_eq!(args.frames, 120);
+        assert_eq!(args.config.lives(), 5);
+        assert_eq!(args.config.keys["fire"], "Up");
+        assert_eq!(args.audio, Some(PathBuf::from("run.wav")));
+    }
+
+    #[test]
+    fn command_line_wins_over_the_config_file() {
+        let args = parse_with_config("lives = 4\nscale = 2\nextra_ship = true", &["--lives", "6"]).unwrap();
+        assert_eq!(args.config.lives(), 6);
+        assert_eq!(args.config.scale, Some(2));
+        assert!(args.config.extra_ship());
+    }
+
+    #[test]
+    fn bad_arguments_are_reported() {
+        assert!(parse(&["--turbo"]).err().unwrap().starts_with("unknown argument: --turbo"));
+        assert_eq!(parse(&["--lives"]).err().unwrap(), "--lives expects a value");
+        assert_eq!(parse(&["--frames", "many"]).err().unwrap(), "invalid frame count: many");
+        assert_eq!(parse(&["--bind", "fire"]).err().unwrap(), "expected BUTTON=KEY, got fire");
+        assert!(parse(&["--record-movie", "a.mov", "--play-movie", "b.mov"]).is_err());
+    }
+
+    #[test]
+    fn out_of_range_settings_are_rejected() {
+        assert!(parse(&["--lives", "7"]).err().unwrap().contains("between 3 and 6"));
+        assert!(parse(&["--lives", "-1"]).err().unwrap().contains("invalid number of lives"));
+        assert!(parse(&["--scale", "0"]).err().unwrap().contains("at least 1"));
+        assert!(parse_with_config("lives = 2", &[]).err().unwrap().contains("between 3 and 6"));
+    }
+}
@@ -1,73 +1,102 @@
-use sdl2::mixer::{Chunk, AUDIO_S16LSB, DEFAULT_CHANNELS};
+use crate::{config::Config, sdl_context::SdlContext};
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
+use space_invaders_emulator::{
+    machine::PortWrite,
+    mixer::{SoundMixer, SAMPLE_RATE},
+    wav::WavWriter,
+};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
+// Audio queued beyond this much is dropped, it only piles up when emulation runs ahead of the sound card
+const MAX_QUEUED_SECONDS: u32 = 1;
+const MAX_QUEUED_BYTES: u32 = MAX_QUEUED_SECONDS * SAMPLE_RATE * 2;
+
+/// Sound output: the mixer renders every frame's port writes and the result is queued to SDL.
+/// Without an audio device the mixer still runs and nothing is heard.
 pub struct AudioDevice {
-    sounds: Vec<Chunk>,
+    mixer: SoundMixer,
+    queue: Option<AudioQueue<i16>>,
     pub muted: bool,
-    prev_port3: u8,
+    /// WAV file the mixed sound is recorded to, muting does not affect it
+    capture: Option<(PathBuf, WavWriter)>,
 }
 
 impl AudioDevice {
-    pub fn new() -> Self {
-        sdl2::mixer::open_audio(44_100, AUDIO_S16LSB, DEFAULT_CHANNELS, 1_024).unwrap();
-        sdl2::mixer::allocate_channels(10);
+    /// Loads the samples, or synthesizes them when asked to or when they cannot be read.
+    pub fn new(config: &Config) -> Self {
+        AudioDevice {
+            mixer: SoundMixer::new(config.sounds(SAMPLE_RATE), SAMPLE_RATE),
+            queue: None,
+            muted: config.muted(),
+            capture: None,
+        }
+    }
 
-        AudioDevice { sounds: Self::load_sounds(), muted: false, prev_port3: 0 }
+    /// Opens the audio device, reporting and staying silent if there is none.
+    pub fn open(&mut self, sdl_context: &SdlContext) {
+        let spec = AudioSpecDesired { freq: Some(SAMPLE_RATE as i32), channels: Some(1), samples: Some(1_024) };
+        match sdl_context.audio().and_then(|audio| audio.open_queue::<i16, _>(None, &spec)) {
+            Ok(queue) => {
+                queue.resume();
+                self.queue = Some(queue);
+            }
+            Err(err) => eprintln!("No audio device ({}), running without sound", err),
+        }
     }
 
-    pub fn load_sounds() -> Vec<Chunk> {
-        std::fs::read_dir("./sounds/").unwrap().map(|entry| {
-            sdl2::mixer::Chunk::from_file(entry.unwrap().path()).unwrap()
-        }).collect()
+    pub fn mute_unmute(&mut self) {
+        self.muted = !self.muted;
     }
 
-    fn play_sound(&self, num: i32) {
-        if !sdl2::mixer::Channel(num).is_playing()
-        {
-            sdl2::mixer::Channel(num).play(&self.sounds[num as usize], 0).unwrap();
+    /// Starts recording to a new file in `dir`, or stops the recording in progress.
+    pub fn toggle_capture(&mut self, dir: &Path) {
+        if let Some((path, _)) = self.capture.take() {
+            println!("Stopped recording audio to {}", path.display());
+            return;
         }
-    }
 
-    pub fn mute_unmute(&mut self) {
-        if self.muted {
-            for i in 0..8 {
-                sdl2::mixer::Channel(i).set_volume(128 as i32);
-            }
-        } else {
-            for i in 0..8 {
-                sdl2::mixer::Channel(i).set_volume(0);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+        let path = dir.join(format!("audio-{}.wav", timestamp));
+        match WavWriter::create(&path, SAMPLE_RATE) {
+            Ok(writer) => {
+                println!("Recording audio to {}", path.display());
+                self.capture = Some((path, writer));
             }
+            Err(err) => eprintln!("Could not record audio to {}: {}", path.display(), err),
         }
-        self.muted = !self.muted;
     }
 
-    pub fn play(&mut self, port: u8, acc: u8) {
-        match port {
-            0x3 => {
-                if acc & (0x1 << 0) != 0 { // UFO sound
-                    self.play_sound(0);
-                } else if acc & (0x1 << 1) != 0 && self.prev_port3 & (0x1 << 1) == 0 { // Shot
-                    self.play_sound(1);
-                } else if acc & (0x1 << 2) != 0 { // Flash (player die)
-                    self.play_sound(2);
-                } else if acc & (0x1 << 3) != 0 { // Invader die
-                    self.play_sound(3);
-                }
-                self.prev_port3 = acc;
+    pub fn capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    /// Takes over the sound latches of a restored or reset machine: whatever was playing stops
+    /// and the UFO siren resumes if its bit is set.
+    pub fn restore_latches(&mut self, port3: u8, port5: u8, cycle: u64) {
+        self.mixer.restore(port3, port5, cycle);
+        if let Some(queue) = &self.queue {
+            queue.clear();
+        }
+    }
+
+    /// Renders the sound of the frame that just ran, up to the machine's cycle count `until`.
+    pub fn play(&mut self, writes: &[PortWrite], until: u64, clock_rate: u32) {
+        let samples = self.mixer.render(writes, until, clock_rate);
+        if let Some((path, writer)) = &mut self.capture {
+            if let Err(err) = writer.write(&samples) {
+                eprintln!("Stopped recording audio to {}: {}", path.display(), err);
+                self.capture = None;
+            }
+        }
+        if let Some(queue) = &self.queue {
+            if self.muted {
+                return;
             }
-            0x5 => {
-                if acc & (0x1 << 0) != 0 { // Fleet movement 1
-                    self.play_sound(4);
-                } else if acc & (0x1 << 1) != 0 { // Fleet movement 2
-                    self.play_sound(5);
-                } else if acc & (0x1 << 2) != 0 { // Fleet movement 3
-                    self.play_sound(6);
-                } else if acc & (0x1 << 3) != 0 { // Fleet movement 4
-                    self.play_sound(7);
-                } else if acc & (0x1 << 4) != 0 { // UFO Hit
-                    self.play_sound(8);
-                }
+            if queue.size() > MAX_QUEUED_BYTES {
+                queue.clear();
             }
-            _ => unreachable!()
+            queue.queue(&samples);
         }
     }
-}
\ No newline at end of file
+}
@@ -1,77 +1,125 @@
 use crate::{
-    shift_register::ShiftRegister,
-    screen::ScreenDevice,
+    args::Args,
+    error::EmulatorError,
     audio::AudioDevice,
-    controls::{Controls, EmulatorAction},
+    console::DebugConsole,
     sdl_context::SdlContext,
-    i8080::I8080
+    input::{send_input, KeyBindings},
+    slot_picker::SlotPicker,
 };
-use std::fs::File;
-use std::io::{Error, Read};
-use std::time::{Instant, Duration};
+use space_invaders_emulator::{
+    screen::{ScreenDevice, SCREEN_WIDTH, SCREEN_HEIGHT, THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT},
+    controls::EmulatorAction,
+    machine::Machine,
+    rom::load_si_rom,
+    save_state,
+    rewind::RewindBuffer,
+    movie::{Movie, MoviePlayer},
+};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 use sdl2::pixels::PixelFormatEnum;
-use sdl2::render::Texture;
-
+use sdl2::render::{Texture, WindowCanvas};
 
-pub fn load_si_rom() -> Result<Vec<u8>, Error> {
-    let rom_h: Vec<_> = File::open("./rom/invaders.h")?.bytes().collect();
-    let rom_g: Vec<_> = File::open("./rom/invaders.g")?.bytes().collect();
-    let rom_f: Vec<_> = File::open("./rom/invaders.f")?.bytes().collect();
-    let rom_e: Vec<_> = File::open("./rom/invaders.e")?.bytes().collect();
 
-    let rom = rom_h
-        .iter()
-        .chain(rom_g.iter())
-        .chain(rom_f.iter())
-        .chain(rom_e.iter())
-        .flatten()
-        .copied()
-        .collect::<Vec<u8>>();
-
-    Ok(rom)
-}
+const SAVE_STATE_DIR: &str = "./saves/";
+const AUDIO_CAPTURE_DIR: &str = "./captures/";
+// Ten seconds of gameplay at 60 frames per second, one snapshot per frame
+const REWIND_CAPACITY: usize = 600;
+const REWIND_INTERVAL: usize = 1;
 
 
+/// SDL frontend around the headless `Machine`: window, keyboard and sound.
 pub struct MainBus{
     audio: AudioDevice,
     screen: ScreenDevice,
-    cpu: I8080,
-    shift_register: ShiftRegister,
-    controls: Controls,
+    machine: Machine,
+    slot_picker: SlotPicker,
+    rewind: RewindBuffer,
+    key_bindings: KeyBindings,
+    rom_dir: PathBuf,
+    scale: Option<u32>,
+    cocktail: bool,
+    record_movie: Option<PathBuf>,
+    play_movie: Option<PathBuf>,
+    recording: Option<Movie>,
+    player: Option<MoviePlayer>,
+    console: DebugConsole,
 }
 
 impl MainBus {
-    pub fn new() -> Self {
-        MainBus {
-            audio: AudioDevice::new(),
+    pub fn new(args: &Args) -> Result<Self, EmulatorError> {
+        let mut machine = Machine::new();
+        machine.bus.log_illegal_writes = args.log_illegal_writes;
+        machine.bus.controls.lives = args.config.lives();
+        machine.bus.controls.extra_ship = args.config.extra_ship();
+
+        Ok(MainBus {
+            audio: AudioDevice::new(&args.config),
             screen: ScreenDevice::new(),
-            cpu: I8080::new(),
-            shift_register: ShiftRegister::new(),
-            controls: Controls::new(),
-        }
+            machine,
+            slot_picker: SlotPicker::new(Path::new(SAVE_STATE_DIR)),
+            rewind: RewindBuffer::new(REWIND_CAPACITY, REWIND_INTERVAL),
+            key_bindings: KeyBindings::new(&args.config.keys).map_err(EmulatorError::Config)?,
+            rom_dir: args.config.rom_dir(),
+            scale: args.config.scale,
+            cocktail: args.config.cocktail(),
+            record_movie: args.record_movie.clone(),
+            play_movie: args.play_movie.clone(),
+            recording: None,
+            player: None,
+            console: DebugConsole::new(args.debug),
+        })
     }
 
 
-    pub fn run(&mut self) -> std::io::Result<()> {
-        self.cpu.load_rom(&load_si_rom()?, 0x0);
+    pub fn run(&mut self) -> Result<(), EmulatorError> {
+        let rom = load_si_rom(&self.rom_dir).map_err(|err| EmulatorError::Rom { path: self.rom_dir.clone(), err })?;
+        self.machine.load_rom(&rom);
 
-        let mut sdl_context = SdlContext::new();
+        let mut sdl_context = SdlContext::new(self.scale)?;
+        self.audio.open(&sdl_context);
         let mut screen_texture = sdl_context.texture_creator
-            .create_texture_streaming(PixelFormatEnum::RGB24, 224,256 ).unwrap();
+            .create_texture_streaming(PixelFormatEnum::RGB24, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+            .map_err(|err| EmulatorError::Sdl(err.to_string()))?;
+        let mut thumbnail_texture = sdl_context.texture_creator
+            .create_texture_streaming(PixelFormatEnum::RGB24, THUMBNAIL_WIDTH as u32, THUMBNAIL_HEIGHT as u32)
+            .map_err(|err| EmulatorError::Sdl(err.to_string()))?;
 
-        let mut emulator_save_state = [0_u8; 0x2000];
         let mut fps = 60.0;
         let mut clock_rate: u32 = 2_000_000;
+        if let Some(path) = &self.play_movie {
+            let movie = Movie::load(path, self.machine.rom_crc)
+                .map_err(|err| EmulatorError::Movie { path: path.clone(), err })?;
+            let player = MoviePlayer::start(movie, &mut self.machine);
+            clock_rate = player.clock_rate();
+            fps = player.fps();
+            self.player = Some(player);
+        }
+        if self.record_movie.is_some() {
+            self.recording = Some(Movie::record(&self.machine, clock_rate, fps));
+        }
+
+        let mut rewinding = false;
         'running: loop {
             let start = Instant::now();
             sdl_context.canvas.clear();
 
-            let action = self.controls.send_input(&mut sdl_context.event_pump);
+            let mut action = send_input(&mut sdl_context.event_pump, &mut self.machine.bus.controls, &self.key_bindings);
+            if self.movie_active() && Self::breaks_movie(&action) {
+                eprintln!("Not available while a movie is recording or playing");
+                action = EmulatorAction::Nothing;
+            }
             match action {
                 EmulatorAction::Nothing => {},
                 EmulatorAction::Quit => break 'running,
-                EmulatorAction::SaveState => emulator_save_state.copy_from_slice(self.cpu.memory[0x2000..0x4000].as_ref()),
-                EmulatorAction::LoadState => self.cpu.memory[0x2000..0x4000].copy_from_slice(emulator_save_state[..].as_ref()),
+                EmulatorAction::SaveState => self.save_state(),
+                EmulatorAction::LoadState => self.load_state(),
+                EmulatorAction::SelectSlot(slot) => self.slot_picker.select(slot),
+                EmulatorAction::PreviousSlot => self.slot_picker.previous(),
+                EmulatorAction::NextSlot => self.slot_picker.next(),
+                EmulatorAction::StartRewind => rewinding = true,
+                EmulatorAction::StopRewind => rewinding = false,
                 EmulatorAction::IncreaseFPS => {clock_rate += 100_000; fps += 5.0;},
                 EmulatorAction::DecreaseFPS => {
                     if clock_rate > 100_000 && fps > 5.0 {
@@ -79,17 +127,45 @@ impl MainBus {
                         clock_rate -= 100_000;
                     }
                 },
-                EmulatorAction::Reset => for i in &mut self.cpu.memory[0x2000..0x4000] { *i = 0; },
+                EmulatorAction::Reset(kind) => {
+                    self.machine.reset(kind);
+                    self.rewind.clear();
+                    self.audio.restore_latches(self.machine.bus.port3, self.machine.bus.port5, self.machine.bus.cycles);
+                },
                 EmulatorAction::Mute => self.audio.mute_unmute(),
+                EmulatorAction::ToggleAudioCapture => self.audio.toggle_capture(Path::new(AUDIO_CAPTURE_DIR)),
+                EmulatorAction::BreakIntoDebugger => self.console.debugger.pause(),
             }
 
-            self.execute_n_cycles((0.5 * clock_rate as f64 / fps) as usize);
-            self.cpu.generate_interrupt(1);
-
-            self.execute_n_cycles((0.5 * clock_rate as f64 / fps) as usize);
-            self.cpu.generate_interrupt(2);
+            if rewinding {
+                if self.rewind.rewind(&mut self.machine) {
+                    self.audio.restore_latches(self.machine.bus.port3, self.machine.bus.port5, self.machine.bus.cycles);
+                }
+            } else {
+                if let Some(player) = &mut self.player {
+                    if !player.next_frame(&mut self.machine) {
+                        println!("Movie finished, the keyboard has control again");
+                        self.player = None;
+                    }
+                }
+                if let Some(movie) = &mut self.recording {
+                    movie.record_frame(&self.machine);
+                }
+
+                self.rewind.capture(&self.machine);
+                if !self.console.run_frame(&mut self.machine, clock_rate, fps) {
+                    break 'running;
+                }
+            }
+            let sound_writes = self.machine.take_sound_writes();
+            self.audio.play(&sound_writes, self.machine.bus.cycles, clock_rate);
+            for (address, value) in self.machine.bus.take_illegal_writes() {
+                eprintln!("illegal write of {:02X} to {:04X}", value, address);
+            }
 
-            self.screen.draw(&mut screen_texture, &mut sdl_context.canvas, &self.cpu.memory).unwrap();
+            self.screen.flipped = self.cocktail && self.machine.bus.flip_screen();
+            self.draw(&mut screen_texture, &mut sdl_context.canvas).map_err(EmulatorError::Sdl)?;
+            self.slot_picker.draw(&mut sdl_context.canvas, &mut thumbnail_texture).map_err(EmulatorError::Sdl)?;
             sdl_context.canvas.present();
 
             sdl_context.sleep_for(start.elapsed(), fps);
@@ -97,52 +173,78 @@ impl MainBus {
             // display some info in windows title
             sdl_context.canvas.window_mut().set_title(
                 format!("Space Invaders Emulator. FPS: {:.2}; Clock rate: {}; \
-                        Start lives: {}; Extra ship: {}; Muted: {}", 1000.0 / start.elapsed().as_millis() as f64,
-                        clock_rate, self.controls.lives, self.controls.extra_ship, self.audio.muted).as_ref()).unwrap();
+                        Start lives: {}; Extra ship: {}; Muted: {}; Save slot: {}{}", 1000.0 / start.elapsed().as_millis() as f64,
+                        clock_rate, self.machine.bus.controls.lives, self.machine.bus.controls.extra_ship, self.audio.muted, self.slot_picker.slot,
+                        if self.audio.capturing() { "; Recording audio" } else { "" }).as_ref())
+                .map_err(|err| EmulatorError::Sdl(err.to_string()))?;
+        }
+
+        if let (Some(movie), Some(path)) = (&self.recording, &self.record_movie) {
+            movie.save(path).map_err(|err| EmulatorError::Io { path: path.clone(), err })?;
         }
 
         Ok(())
     }
 
-    fn execute_n_cycles(&mut self, n: usize) -> Option<()> {
-        let mut current_rate = 0;
-        while current_rate < n {
-            let instr = self.cpu.read_instr()?;
-            self.intercept_instr(instr);
+    fn movie_active(&self) -> bool {
+        self.recording.is_some() || self.player.is_some()
+    }
 
-            let len = self.cpu.execute(instr);
-            current_rate += len;
-        }
-        Some(())
+    /// Actions that would make a movie diverge from the inputs it records or replays.
+    fn breaks_movie(action: &EmulatorAction) -> bool {
+        matches!(action,
+            EmulatorAction::LoadState | EmulatorAction::StartRewind | EmulatorAction::Reset(_)
+            | EmulatorAction::IncreaseFPS | EmulatorAction::DecreaseFPS)
     }
 
-    fn intercept_instr(&mut self, instr: u8) {
-        match instr {
-            0xd3 => self.write_port(self.cpu.read_memory(self.cpu.pc + 1), self.cpu.a),
-            0xdb => self.cpu.a = self.read_port(self.cpu.read_memory(self.cpu.pc + 1)),
-            _ => {}
-        }
+    fn draw(&self, texture: &mut Texture, canvas: &mut WindowCanvas) -> Result<(), String> {
+        let memory = &self.machine.bus.memory;
+        texture.with_lock(None, |buffer: &mut [u8], pitch: usize| {
+            self.screen.draw(buffer, pitch, memory);
+        })?;
+
+        canvas.copy_ex(texture, None, None, 0.0, None, false, true)?;
+        Ok(())
     }
 
-    pub fn read_port(&self, port: u8) -> u8 {
-        match port {
-            0 => self.controls.read_controls(port), //
-            1 => self.controls.read_controls(port), // 1st player
-            2 => self.controls.read_controls(port), // 2nd player
-            3 => self.shift_register.read_value(),
-            _ => unreachable!()
+    fn save_state(&mut self) {
+        let path = self.slot_picker.slot_path();
+        let state = self.machine.save_state();
+        let thumbnail = self.screen.thumbnail(&self.machine.bus.memory);
+        if let Err(err) = save_state::save_to_file(&path, &state, self.machine.rom_crc, &thumbnail) {
+            eprintln!("Could not save state to {}: {}", path.display(), err);
         }
+        self.slot_picker.refresh();
+        self.slot_picker.show();
     }
 
-    pub fn write_port(&mut self, port: u8, acc: u8) {
-        match port {
-            2 => self.shift_register.set_shift_amount(acc),
-            3 => self.audio.play(port, acc), // discrete sounds
-            4 => self.shift_register.put_value(acc),
-            5 => self.audio.play(port, acc),     // another sound
-            6 => { } // watch-dog timer?
-            _ => unreachable!()
+    fn load_state(&mut self) {
+        let path = self.slot_picker.slot_path();
+        match save_state::load_from_file(&path, self.machine.rom_crc) {
+            Ok((_, state)) => {
+                self.machine.load_state(&state);
+                // frames from before the load must not be rewound into
+                self.rewind.clear();
+                self.audio.restore_latches(self.machine.bus.port3, self.machine.bus.port5, self.machine.bus.cycles);
+            }
+            Err(err) => eprintln!("Could not load state from {}: {}", path.display(), err),
         }
+        self.slot_picker.show();
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_key_name_is_a_config_error() {
+        let mut args = Args::default();
+        args.config.synth = Some(true);
+        args.config.keys.insert("fire".to_string(), "NoSuchKey".to_string());
+
+        let err = MainBus::new(&args).err().unwrap();
+        assert!(matches!(err, EmulatorError::Config(_)));
+        assert!(err.to_string().contains("NoSuchKey"));
+    }
+}
@@ -0,0 +1,168 @@
+use serde::Deserialize;
+use space_invaders_emulator::{controls::Button, rom::DEFAULT_ROM_DIR, sound};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+
+// Read when it exists and no --config is given
+pub const DEFAULT_CONFIG: &str = "./invaders.toml";
+
+/// Settings shared by the config file and the command line, unset fields fall back to defaults.
+///
+/// ```toml
+/// rom_dir = "./rom"
+/// sound_dir = "./sounds"
+/// scale = 3
+/// lives = 5
+/// extra_ship = true
+/// muted = false
+/// synth = false
+/// cocktail = false
+///
+/// [keys]
+/// fire = "Up"
+/// coin = "Return"
+/// ```
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub rom_dir: Option<PathBuf>,
+    pub sound_dir: Option<PathBuf>,
+    /// Window size as a multiple of 224x256, by default the window fills most of the screen
+    pub scale: Option<u32>,
+    pub lives: Option<u8>,
+    pub extra_ship: Option<bool>,
+    pub muted: Option<bool>,
+    /// Synthesize every sound instead of playing the samples in `sound_dir`
+    pub synth: Option<bool>,
+    /// Cocktail table: the screen turns around during the second player's turns
+    pub cocktail: Option<bool>,
+    /// Button name (`Button::from_name`) to SDL key name
+    pub keys: BTreeMap<String, String>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        toml::from_str(&text).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    /// Settings from `other` win over ours.
+    pub fn merge(mut self, other: Config) -> Self {
+        self.rom_dir = other.rom_dir.or(self.rom_dir);
+        self.sound_dir = other.sound_dir.or(self.sound_dir);
+        self.scale = other.scale.or(self.scale);
+        self.lives = other.lives.or(self.lives);
+        self.extra_ship = other.extra_ship.or(self.extra_ship);
+        self.muted = other.muted.or(self.muted);
+        self.synth = other.synth.or(self.synth);
+        self.cocktail = other.cocktail.or(self.cocktail);
+        self.keys.extend(other.keys);
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(lives) = self.lives {
+            if !(3..=6).contains(&lives) {
+                return Err(format!("start lives must be between 3 and 6, got {}", lives));
+            }
+        }
+        if self.scale == Some(0) {
+            return Err("window scale must be at least 1".to_string());
+        }
+        for button in self.keys.keys() {
+            if Button::from_name(button).is_none() {
+                return Err(format!("unknown button in key bindings: {}", button));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn rom_dir(&self) -> PathBuf {
+        self.rom_dir.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_ROM_DIR))
+    }
+
+    pub fn sound_dir(&self) -> PathBuf {
+        self.sound_dir.clone().unwrap_or_else(|| PathBuf::from("./sounds"))
+    }
+
+    pub fn lives(&self) -> u8 {
+        self.lives.unwrap_or(3)
+    }
+
+    pub fn extra_ship(&self) -> bool {
+        self.extra_ship.unwrap_or(false)
+    }
+
+    #[cfg(feature = "sdl")]
+    pub fn muted(&self) -> bool {
+        self.muted.unwrap_or(false)
+    }
+
+    pub fn cocktail(&self) -> bool {
+        self.cocktail.unwrap_or(false)
+    }
+
+    pub fn synth(&self) -> bool {
+        self.synth.unwrap_or(false)
+    }
+
+    /// PCM of every sound as configured, samples that cannot be loaded are reported and synthesized.
+    pub fn sounds(&self, sample_rate: u32) -> Vec<Vec<i16>> {
+        if self.synth() {
+            return sound::synthesize_sounds(sample_rate);
+        }
+
+        let (sounds, problems) = sound::load_sounds(&self.sound_dir(), sample_rate);
+        for problem in problems {
+            eprintln!("Could not load {}", problem);
+        }
+        sounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_settings_come_from_the_other_config_first() {
+        let file: Config = toml::from_str("lives = 4\nscale = 2\n[keys]\nfire = \"Up\"\ncoin = \"Return\"").unwrap();
+        let command_line = Config { lives: Some(5), keys: vec![("fire".to_string(), "Z".to_string())].into_iter().collect(), ..Config::default() };
+        let config = file.merge(command_line);
+
+        assert_eq!(config.lives(), 5);
+        assert_eq!(config.scale, Some(2));
+        assert_eq!(config.keys["fire"], "Z");
+        assert_eq!(config.keys["coin"], "Return");
+        assert!(!config.extra_ship());
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_values() {
+        for lives in [2, 7] {
+            let config = Config { lives: Some(lives), ..Config::default() };
+            assert!(config.validate().err().unwrap().contains("between 3 and 6"));
+        }
+        for lives in 3..=6 {
+            assert!(Config { lives: Some(lives), ..Config::default() }.validate().is_ok());
+        }
+
+        assert!(Config { scale: Some(0), ..Config::default() }.validate().is_err());
+        assert!(Config { scale: Some(1), ..Config::default() }.validate().is_ok());
+
+        let keys = vec![("jump".to_string(), "Up".to_string())].into_iter().collect();
+        assert!(Config { keys, ..Config::default() }.validate().err().unwrap().contains("jump"));
+    }
+
+    #[test]
+    fn unknown_settings_are_an_error_naming_the_file() {
+        let path = std::env::temp_dir().join(format!("unknown-setting-{}.toml", std::process::id()));
+        std::fs::write(&path, "lifes = 4\n").unwrap();
+        let err = Config::load(&path).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(err.contains(&path.display().to_string()));
+        assert!(err.contains("lifes"));
+    }
+}
@@ -0,0 +1,164 @@
+use space_invaders_emulator::{
+    debugger::Debugger,
+    disassembler::{load_mnemonics_file, print_instr_description},
+    machine::Machine,
+};
+use std::io::{BufRead, Write};
+
+const HELP: &str = "\
+Commands (an empty line repeats the last one):
+    s, step [N]     Execute N instructions (default 1)
+    n, next         Step over a CALL or RST, run until it returns
+    c, continue     Run until a breakpoint is hit
+    b, break [ADDR] Set a breakpoint at the hex address ADDR, without ADDR list them
+    d, delete ADDR  Remove the breakpoint at ADDR
+    r, regs         Show registers, flags and the instruction at PC
+    q, quit         Exit the emulator
+    h, help         Print this message";
+
+enum Prompt {
+    /// The debugger was resumed
+    Run,
+    /// A step finished the frame, the frontend gets to present it before the prompt comes back
+    FrameDone,
+    Quit,
+}
+
+/// Terminal prompt for the debugger. The emulator waits while it reads commands from stdin.
+pub struct DebugConsole {
+    pub debugger: Debugger,
+    /// Loaded the first time an instruction is shown, None if that failed
+    mnemonics: Option<Option<Vec<Vec<String>>>>,
+    last_command: String,
+}
+
+impl DebugConsole {
+    pub fn new(paused: bool) -> Self {
+        let mut debugger = Debugger::new();
+        if paused {
+            debugger.pause();
+        }
+        DebugConsole { debugger, mnemonics: None, last_command: String::new() }
+    }
+
+    /// Runs one frame, prompting for commands whenever the debugger stops.
+    /// Returns false if the user quit.
+    pub fn run_frame(&mut self, machine: &mut Machine, clock_rate: u32, fps: f64) -> bool {
+        loop {
+            if self.debugger.paused() {
+                match self.prompt(machine, clock_rate, fps) {
+                    Prompt::Run => {}
+                    Prompt::FrameDone => return true,
+                    Prompt::Quit => return false,
+                }
+            }
+            if self.debugger.run(machine, clock_rate, fps) {
+                return true;
+            }
+        }
+    }
+
+    fn prompt(&mut self, machine: &mut Machine, clock_rate: u32, fps: f64) -> Prompt {
+        if self.debugger.is_breakpoint(machine.cpu.pc) {
+            println!("Breakpoint at {:04X}", machine.cpu.pc);
+        }
+        self.show_state(machine);
+
+        let stdin = std::io::stdin();
+        loop {
+            print!("(debug) ");
+            let _ = std::io::stdout().flush();
+
+            let mut line = String::new();
+            match stdin.lock().read_line(&mut line) {
+                Ok(0) | Err(_) => return Prompt::Quit,
+                Ok(_) => {}
+            }
+            if line.trim().is_empty() {
+                line = self.last_command.clone();
+            } else {
+                self.last_command = line.clone();
+            }
+
+            let mut words = line.split_whitespace();
+            let command = words.next().unwrap_or("");
+            let argument = words.next();
+            match command {
+                "" => {}
+                "s" | "step" => {
+                    let count = match argument.map(str::parse::<usize>) {
+                        None => 1,
+                        Some(Ok(count)) => count,
+                        Some(Err(_)) => {
+                            println!("Not a number of instructions: {}", argument.unwrap_or(""));
+                            continue;
+                        }
+                    };
+                    for _ in 0..count {
+                        if self.debugger.step(machine, clock_rate, fps) {
+                            return Prompt::FrameDone;
+                        }
+                    }
+                    self.show_state(machine);
+                }
+                "n" | "next" => {
+                    if self.debugger.step_over(machine, clock_rate, fps) {
+                        return Prompt::FrameDone;
+                    }
+                    if !self.debugger.paused() {
+                        return Prompt::Run;
+                    }
+                    self.show_state(machine);
+                }
+                "c" | "continue" => {
+                    self.debugger.resume();
+                    return Prompt::Run;
+                }
+                "b" | "break" => match argument.map(parse_address) {
+                    None => {
+                        let breakpoints = self.debugger.breakpoints().map(|address| format!("{:04X}", address)).collect::<Vec<_>>();
+                        println!("Breakpoints: {}", if breakpoints.is_empty() { "none".to_string() } else { breakpoints.join(", ") });
+                    }
+                    Some(Some(address)) => {
+                        self.debugger.add_breakpoint(address);
+                        println!("Breakpoint set at {:04X}", address);
+                    }
+                    Some(None) => println!("Not an address: {}", argument.unwrap_or("")),
+                },
+                "d" | "delete" => match argument.and_then(parse_address) {
+                    Some(address) if self.debugger.remove_breakpoint(address) => println!("Breakpoint at {:04X} removed", address),
+                    Some(address) => println!("No breakpoint at {:04X}", address),
+                    None => println!("delete expects a hex address"),
+                },
+                "r" | "regs" => self.show_state(machine),
+                "q" | "quit" => return Prompt::Quit,
+                "h" | "help" => println!("{}", HELP),
+                _ => println!("Unknown command: {}, type help for the list", command),
+            }
+        }
+    }
+
+    fn show_state(&mut self, machine: &Machine) {
+        machine.cpu.print_state(&machine.bus);
+        if machine.cpu.halted {
+            println!("\tHalted, waiting for an interrupt");
+        }
+
+        let mnemonics = self.mnemonics.get_or_insert_with(|| match load_mnemonics_file() {
+            Ok(mnemonics) => Some(mnemonics),
+            Err(err) => {
+                eprintln!("Could not load the mnemonics, no disassembly: {}", err);
+                None
+            }
+        });
+        if let Some(mnemonics) = mnemonics {
+            let _ = print_instr_description(mnemonics, &machine.bus, machine.cpu.pc);
+        }
+    }
+}
+
+/// A hex address, with or without a 0x or $ prefix.
+fn parse_address(text: &str) -> Option<usize> {
+    let digits = text.trim_start_matches("0x").trim_start_matches('$');
+    usize::from_str_radix(digits, 16).ok().filter(|&address| address <= 0xFFFF)
+}
@@ -1,21 +1,61 @@
-use sdl2::keyboard::Keycode;
-use sdl2::event::Event;
-use sdl2::EventPump;
-use std::process::exit;
+use crate::save_state::{StateError, StateReader};
+use crate::machine::ResetKind;
 
 pub enum EmulatorAction {
     Nothing,
     Quit,
     SaveState,
     LoadState,
+    SelectSlot(usize),
+    PreviousSlot,
+    NextSlot,
+    StartRewind,
+    StopRewind,
     IncreaseFPS,
     DecreaseFPS,
-    Reset,
-    Mute
+    Reset(ResetKind),
+    Mute,
+    ToggleAudioCapture,
+    BreakIntoDebugger,
+}
+
+/// Cabinet buttons and switches, independent of any keyboard mapping.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Button {
+    P1Start,
+    P2Start,
+    Fire,
+    Left,
+    Right,
+    Coin,
+    Tilt,
+}
+
+impl Button {
+    pub const ALL: [Button; 7] = [
+        Button::P1Start, Button::P2Start, Button::Fire, Button::Left, Button::Right, Button::Coin, Button::Tilt,
+    ];
+
+    /// Name used for the button in config files and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Button::P1Start => "p1_start",
+            Button::P2Start => "p2_start",
+            Button::Fire => "fire",
+            Button::Left => "left",
+            Button::Right => "right",
+            Button::Coin => "coin",
+            Button::Tilt => "tilt",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Button::ALL.iter().copied().find(|button| button.name() == name)
+    }
 }
 
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Controls {
     p1: bool,
     p2: bool,
@@ -38,70 +78,35 @@ impl Controls {
 
     }
 
-    pub fn send_input(&mut self, event_pump: &mut EventPump) -> EmulatorAction {
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. } | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => return EmulatorAction::Quit,
-
-                Event::KeyDown { keycode: Some(Keycode::Space), .. } => self.fire = true,
-                Event::KeyUp { keycode: Some(Keycode::Space), .. } => self.fire = false,
-
-                Event::KeyDown { keycode: Some(Keycode::Num1), .. } => self.p1 = true,
-                Event::KeyUp { keycode: Some(Keycode::Num1), .. } => self.p1 = false,
-
-                Event::KeyDown { keycode: Some(Keycode::Num2), .. } => self.p2 = true,
-                Event::KeyUp { keycode: Some(Keycode::Num2), .. } => self.p2 = false,
-
-                Event::KeyDown { keycode: Some(Keycode::Left), .. } => self.left = true,
-                Event::KeyUp { keycode: Some(Keycode::Left), .. } => self.left = false,
-
-                Event::KeyDown { keycode: Some(Keycode::Right), .. } => self.right = true,
-                Event::KeyUp { keycode: Some(Keycode::Right), .. } => self.right = false,
-
-                Event::KeyDown { keycode: Some(Keycode::C), .. } => self.coin_slot = true,
-                Event::KeyUp { keycode: Some(Keycode::C), .. } => self.coin_slot = false,
-
-                Event::KeyDown { keycode: Some(Keycode::T), .. } => self.tilt = true,
-                Event::KeyUp { keycode: Some(Keycode::T), .. } => self.tilt = false,
-
-                Event::KeyDown { keycode: Some(Keycode::F1), .. } => return EmulatorAction::SaveState,
-                Event::KeyDown { keycode: Some(Keycode::F2), .. } => return EmulatorAction::LoadState,
-                Event::KeyDown { keycode: Some(Keycode::KpPlus), .. } => return EmulatorAction::IncreaseFPS,
-                Event::KeyDown { keycode: Some(Keycode::KpMinus), .. } => return EmulatorAction::DecreaseFPS,
-                Event::KeyDown { keycode: Some(Keycode::R), .. } => return EmulatorAction::Reset,
-                Event::KeyDown { keycode: Some(Keycode::M), .. } => return EmulatorAction::Mute,
-
-                Event::KeyDown { keycode: Some(Keycode::Num3), .. } => self.lives = 3,
-                Event::KeyDown { keycode: Some(Keycode::Num4), .. } => self.lives = 4,
-                Event::KeyDown { keycode: Some(Keycode::Num5), .. } => self.lives = 5,
-                Event::KeyDown { keycode: Some(Keycode::Num6), .. } => self.lives = 6,
-
-                Event::KeyDown { keycode: Some(Keycode::X), .. } => self.extra_ship = !self.extra_ship,
-                _ => {}
-            }
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        match button {
+            Button::P1Start => self.p1 = pressed,
+            Button::P2Start => self.p2 = pressed,
+            Button::Fire => self.fire = pressed,
+            Button::Left => self.left = pressed,
+            Button::Right => self.right = pressed,
+            Button::Coin => self.coin_slot = pressed,
+            Button::Tilt => self.tilt = pressed,
         }
-
-        EmulatorAction::Nothing
     }
 
-
     pub fn read_controls(&self, port: u8) -> u8 {
         match port {
             0 => {
                 let fire_bit = (self.fire as u8) << 4;
                 let left_bit = (self.left as u8) << 5;
                 let right_bit = (self.right as u8) << 6;
-                return right_bit | left_bit | fire_bit | 0b1111;
+                right_bit | left_bit | fire_bit | 0b1111
             }
             1 => {
-                let put_coin = (self.coin_slot as u8) << 0;
+                let put_coin = self.coin_slot as u8;
                 let p2_start = (self.p2 as u8) << 1;
                 let p1_start = (self.p1 as u8) << 2;
                 let always_true_bit = 1 << 3;
                 let p1_shot = (self.fire as u8) << 4;
                 let p1_left = (self.left as u8) << 5;
                 let p1_right = (self.right as u8) << 6;
-                return p1_right | p1_left | p1_shot | p2_start | p1_start | put_coin | always_true_bit ;
+                p1_right | p1_left | p1_shot | p2_start | p1_start | put_coin | always_true_bit
             }
             2 => {
                 let lives = (self.lives - 3) & 0b11;
@@ -111,10 +116,49 @@ impl Controls {
                 let p1_shot = (self.fire as u8) << 4;
                 let p1_left = (self.left as u8) << 5;
                 let p1_right = (self.right as u8) << 6;
-                return p1_right | p1_left | p1_shot | extra_ship_at_1000 | tilt | lives;
+                p1_right | p1_left | p1_shot | extra_ship_at_1000 | tilt | lives
             }
             _ => unreachable!()
         }
     }
-}
 
+    /// Values of input ports 0, 1 and 2, what a movie records each frame.
+    pub fn ports(&self) -> [u8; 3] {
+        [self.read_controls(0), self.read_controls(1), self.read_controls(2)]
+    }
+
+    /// Inverse of `ports`: ports 1 and 2 carry every button and switch.
+    pub fn set_ports(&mut self, ports: [u8; 3]) {
+        self.coin_slot = ports[1] & (0x1 << 0) != 0;
+        self.p2 = ports[1] & (0x1 << 1) != 0;
+        self.p1 = ports[1] & (0x1 << 2) != 0;
+        self.fire = ports[1] & (0x1 << 4) != 0;
+        self.left = ports[1] & (0x1 << 5) != 0;
+        self.right = ports[1] & (0x1 << 6) != 0;
+
+        self.lives = (ports[2] & 0b11) + 3;
+        self.tilt = ports[2] & (0x1 << 2) != 0;
+        self.extra_ship = ports[2] & (0x1 << 3) != 0;
+    }
+
+    pub(crate) fn write_state(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[
+            self.p1 as u8, self.p2 as u8, self.fire as u8, self.left as u8, self.right as u8,
+            self.coin_slot as u8, self.tilt as u8, self.lives, self.extra_ship as u8,
+        ]);
+    }
+
+    pub(crate) fn read_state(reader: &mut StateReader) -> Result<Self, StateError> {
+        Ok(Controls {
+            p1: reader.bool()?,
+            p2: reader.bool()?,
+            fire: reader.bool()?,
+            left: reader.bool()?,
+            right: reader.bool()?,
+            coin_slot: reader.bool()?,
+            tilt: reader.bool()?,
+            lives: reader.u8()?.clamp(3, 6),
+            extra_ship: reader.bool()?,
+        })
+    }
+}
@@ -0,0 +1,106 @@
+//! Just enough of CP/M to run 8080 test programs like `rom/tests/cpudiag.bin`:
+//! programs are loaded at 0x100, console BDOS calls are captured into a string
+//! and a jump to the warm boot vector at 0x0000 ends the run.
+
+use crate::i8080::{I8080, Bus, Ram};
+
+const WARM_BOOT: usize = 0x0000;
+const BDOS: usize = 0x0005;
+const PROGRAM_START: usize = 0x0100;
+// Top of the transient program area, some programs load their stack pointer from 0x0006
+const BDOS_TOP: usize = 0xF000;
+
+pub struct CpmMachine {
+    pub cpu: I8080,
+    pub ram: Ram,
+    pub console: String,
+}
+
+impl CpmMachine {
+    pub fn new(program: &[u8]) -> Self {
+        let mut cpu = I8080::new();
+        let mut ram = Ram::new();
+        ram.load(program, PROGRAM_START);
+        cpu.pc = PROGRAM_START;
+
+        // Never executed, both addresses are trapped before the instruction is fetched
+        ram.memory[WARM_BOOT] = 0x76; // HLT
+        ram.memory[BDOS] = 0xc3; // JMP BDOS_TOP
+        ram.memory[BDOS + 1] = (BDOS_TOP & 0xFF) as u8;
+        ram.memory[BDOS + 2] = (BDOS_TOP >> 8) as u8;
+
+        CpmMachine { cpu, ram, console: String::new() }
+    }
+
+    /// Runs the program until it exits through the warm boot vector or `max_cycles` are spent.
+    /// Returns true if the program exited by itself.
+    pub fn run(&mut self, max_cycles: u64) -> bool {
+        let mut cycles = 0;
+        while cycles < max_cycles {
+            match self.cpu.pc {
+                WARM_BOOT => return true,
+                BDOS => {
+                    self.bdos_call();
+                    self.cpu.pc = self.cpu.pop(&self.ram); // return to the caller like the real BDOS does
+                }
+                _ if self.cpu.halted => return false, // nothing raises interrupts under CP/M
+                _ => {
+                    let instr = match self.cpu.read_instr(&self.ram) {
+                        Some(instr) => instr,
+                        None => return false,
+                    };
+                    cycles += self.cpu.execute(&mut self.ram, instr) as u64;
+                }
+            }
+        }
+        false
+    }
+
+    fn bdos_call(&mut self) {
+        match self.cpu.c {
+            2 => self.console.push(self.cpu.e as char), // console output
+            9 => { // print string terminated with '$'
+                let mut address = I8080::join_bytes(self.cpu.d, self.cpu.e);
+                while self.ram.read_memory(address) != b'$' {
+                    self.console.push(self.ram.read_memory(address) as char);
+                    address = (address + 1) & 0xFFFF;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+
+/// One line of an exerciser report such as 8080EXM's
+/// `dad <b,d,h,sp>................  PASS! crc is:14474ba6`.
+#[derive(Debug)]
+pub struct ExerciserResult {
+    pub name: String,
+    pub passed: bool,
+    pub crc: Option<u32>,
+    pub report: String,
+}
+
+/// Splits the console output of an exerciser into per-test results.
+/// Lines that are not test reports (banners, "Tests complete") are skipped.
+pub fn exerciser_results(console: &str) -> Vec<ExerciserResult> {
+    console
+        .lines()
+        .filter_map(|line| {
+            let dots = line.find("...")?;
+            let name = line[..dots].trim().to_string();
+            let report = line[dots..].trim_start_matches('.').trim().to_string();
+
+            let passed = !report.contains("ERROR") && (report.contains("OK") || report.contains("PASS"));
+            // on failure this is the CRC the CPU produced, not the expected one
+            let crc = ["found:", "crc is:"]
+                .iter()
+                .filter_map(|key| report.find(key).map(|pos| &report[pos + key.len()..]))
+                .filter_map(|rest| u32::from_str_radix(rest.trim().get(..8)?, 16).ok())
+                .next();
+
+            Some(ExerciserResult { name, passed, crc, report })
+        })
+        .collect()
+}
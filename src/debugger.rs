@@ -0,0 +1,139 @@
+//! Instruction-level control over a `Machine` for debugging frontends: pausing, single steps,
+//! stepping over subroutine calls and PC breakpoints.
+//!
+//! The debugger runs the frame one instruction at a time and fires the mid-screen and vblank
+//! interrupts at the same points `Machine::run_frame` does, so a frame run under it is the
+//! same as one run without it, wherever it was paused.
+
+use crate::{i8080::Bus, machine::Machine};
+use std::collections::BTreeSet;
+
+
+pub struct Debugger {
+    breakpoints: BTreeSet<usize>,
+    paused: bool,
+    /// Set on resuming, so the breakpoint the CPU stands on does not stop it straight away
+    skip_breakpoint: bool,
+    /// Return address and stack pointer of the subroutine being stepped over
+    step_over: Option<(usize, usize)>,
+    /// Cycles run in the current half of the frame
+    frame_cycles: usize,
+    /// Whether the mid-screen interrupt of the current frame has fired
+    second_half: bool,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            paused: false,
+            skip_breakpoint: false,
+            step_over: None,
+            frame_cycles: 0,
+            second_half: false,
+        }
+    }
+
+    /// Returns false if there already was a breakpoint at `address`.
+    pub fn add_breakpoint(&mut self, address: usize) -> bool {
+        self.breakpoints.insert(address)
+    }
+
+    /// Returns false if there was no breakpoint at `address`.
+    pub fn remove_breakpoint(&mut self, address: usize) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn is_breakpoint(&self, address: usize) -> bool {
+        self.breakpoints.contains(&address)
+    }
+
+    /// Stops before the next instruction `run` would execute.
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.step_over = None;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.skip_breakpoint = true;
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Executes one instruction, or idles to the next interrupt if the CPU is halted.
+    /// Returns true if that finished the frame.
+    pub fn step(&mut self, machine: &mut Machine, clock_rate: u32, fps: f64) -> bool {
+        let half_frame = (0.5 * clock_rate as f64 / fps) as usize;
+        let cycles_before = machine.bus.cycles;
+        // a single cycle is always a whole instruction, a halted CPU sits out the rest of the half
+        machine.execute_n_cycles(if machine.cpu.halted { half_frame.saturating_sub(self.frame_cycles) } else { 1 });
+        self.frame_cycles += (machine.bus.cycles - cycles_before) as usize;
+
+        if self.frame_cycles < half_frame {
+            return false;
+        }
+        self.frame_cycles = 0;
+        self.second_half = !self.second_half;
+        if self.second_half {
+            machine.cpu.generate_interrupt(&mut machine.bus, 1);
+            false
+        } else {
+            machine.cpu.generate_interrupt(&mut machine.bus, 2);
+            true
+        }
+    }
+
+    /// Runs a CALL, conditional CALL or RST at PC until it returns, any other instruction is a
+    /// single step. The subroutine runs under `run`, which stops on its return or at a breakpoint.
+    /// Returns true if a single step finished the frame.
+    pub fn step_over(&mut self, machine: &mut Machine, clock_rate: u32, fps: f64) -> bool {
+        let pc = machine.cpu.pc;
+        let len = match machine.bus.read_memory(pc) {
+            0xcd | 0xdd | 0xed | 0xfd | 0xc4 | 0xcc | 0xd4 | 0xdc | 0xe4 | 0xec | 0xf4 | 0xfc => 3,
+            instr if instr & 0xc7 == 0xc7 => 1,
+            _ => return self.step(machine, clock_rate, fps),
+        };
+        self.step_over = Some((pc + len, machine.cpu.sp));
+        self.resume();
+        false
+    }
+
+    /// Runs to the end of the frame unless a breakpoint or the end of a step over pauses the
+    /// debugger first. Returns true if the frame finished, false if it is paused.
+    pub fn run(&mut self, machine: &mut Machine, clock_rate: u32, fps: f64) -> bool {
+        if self.paused {
+            return false;
+        }
+        if self.breakpoints.is_empty() && self.step_over.is_none() && self.frame_cycles == 0 && !self.second_half {
+            machine.run_frame(clock_rate, fps);
+            return true;
+        }
+
+        loop {
+            let pc = machine.cpu.pc;
+            let skip_breakpoint = std::mem::take(&mut self.skip_breakpoint);
+            let returned = matches!(self.step_over,
+                Some((return_address, sp)) if pc == return_address && machine.cpu.sp >= sp);
+            if returned || (self.breakpoints.contains(&pc) && !skip_breakpoint) {
+                self.pause();
+                return false;
+            }
+            if self.step(machine, clock_rate, fps) {
+                return true;
+            }
+        }
+    }
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
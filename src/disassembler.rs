@@ -1,5 +1,6 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use crate::i8080::Bus;
 
 
 pub fn load_mnemonics_file() -> std::io::Result<Vec<Vec<String>>> {
@@ -18,7 +19,7 @@ pub fn load_mnemonics_file() -> std::io::Result<Vec<Vec<String>>> {
     Ok(mnemonics)
 }
 
-pub fn i8080_disassembler(rom: &Vec<std::io::Result<u8>>, offset: usize) -> std::io::Result<()> {
+pub fn i8080_disassembler(rom: &[std::io::Result<u8>], offset: usize) -> std::io::Result<()> {
     let mnemonics = load_mnemonics_file()?;
 
     let get_instr = |num: usize| *rom.get(num).unwrap_or(&Ok(0)).as_ref().unwrap_or(&0) as usize;
@@ -27,7 +28,7 @@ pub fn i8080_disassembler(rom: &Vec<std::io::Result<u8>>, offset: usize) -> std:
     while pc < rom.len() {
         let instr = get_instr(pc);
 
-        let ref current_instr = mnemonics[instr as usize];
+        let current_instr = &mnemonics[instr];
 
         let instr_length: usize = current_instr[2].parse().unwrap_or(1);
         let full_instr = (0..instr_length)
@@ -47,13 +48,14 @@ pub fn i8080_disassembler(rom: &Vec<std::io::Result<u8>>, offset: usize) -> std:
 }
 
 
-pub fn print_instr_description(mnemonics: &Vec<Vec<String>>, memory: &[u8; 0x10000], pc: usize) -> std::io::Result<()> {
+/// Describes the instruction at `pc` as the CPU would fetch it from `bus`.
+pub fn print_instr_description(mnemonics: &[Vec<String>], bus: &impl Bus, pc: usize) -> std::io::Result<()> {
 
-    let ref current_instr = mnemonics[memory[pc] as usize];
+    let current_instr = &mnemonics[bus.read_memory(pc) as usize];
 
     let instr_length = current_instr[2].parse().unwrap_or(1);
     let full_instr = (0..instr_length)
-        .map(|i| mnemonics[memory[pc + i] as usize][0].to_string())
+        .map(|i| mnemonics[bus.read_memory((pc + i) & 0xFFFF) as usize][0].to_string())
         .collect::<Vec<String>>()
         .join(" ");
 
@@ -0,0 +1,35 @@
+use space_invaders_emulator::{rom::RomError, save_state::StateError};
+use std::fmt;
+use std::path::PathBuf;
+
+
+/// Everything that stops the emulator from starting or running, reported by `main`.
+#[derive(Debug)]
+pub enum EmulatorError {
+    Rom { path: PathBuf, err: RomError },
+    Movie { path: PathBuf, err: StateError },
+    /// Bad settings that only show up once the frontend starts, like an unknown key name
+    #[cfg(feature = "sdl")]
+    Config(String),
+    /// SDL could not create the window, renderer or a texture, or failed to draw
+    #[cfg(feature = "sdl")]
+    Sdl(String),
+    /// Writing one of the output files failed
+    Io { path: PathBuf, err: std::io::Error },
+}
+
+impl fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EmulatorError::Rom { path, err } => write!(f, "could not load the ROM set from {}: {}", path.display(), err),
+            EmulatorError::Movie { path, err } => write!(f, "could not load movie {}: {}", path.display(), err),
+            #[cfg(feature = "sdl")]
+            EmulatorError::Config(message) => write!(f, "{}", message),
+            #[cfg(feature = "sdl")]
+            EmulatorError::Sdl(message) => write!(f, "SDL error: {}", message),
+            EmulatorError::Io { path, err } => write!(f, "could not write {}: {}", path.display(), err),
+        }
+    }
+}
+
+impl std::error::Error for EmulatorError {}
@@ -0,0 +1,163 @@
+use crate::{args::Args, console::DebugConsole, error::EmulatorError};
+use space_invaders_emulator::{
+    screen::{ScreenDevice, write_ppm},
+    machine::Machine,
+    rom::load_si_rom,
+    movie::{Movie, MoviePlayer},
+    mixer::{SoundMixer, SAMPLE_RATE},
+    wav::WavWriter,
+};
+
+
+const CLOCK_RATE: u32 = 2_000_000;
+const FPS: f64 = 60.0;
+
+
+/// Runs the machine for a fixed number of frames without a window or sound device,
+/// then dumps the last frame and, if asked, the work RAM. The sound can be rendered to a file.
+pub fn run(args: &Args) -> Result<(), EmulatorError> {
+    let mut machine = Machine::new();
+    let rom_dir = args.config.rom_dir();
+    machine.load_rom(&load_si_rom(&rom_dir).map_err(|err| EmulatorError::Rom { path: rom_dir.clone(), err })?);
+    machine.bus.log_illegal_writes = args.log_illegal_writes;
+    machine.bus.controls.lives = args.config.lives();
+    machine.bus.controls.extra_ship = args.config.extra_ship();
+
+    let (mut clock_rate, mut fps, mut frames) = (CLOCK_RATE, FPS, args.frames);
+    let mut player = None;
+    if let Some(path) = &args.play_movie {
+        let movie = Movie::load(path, machine.rom_crc)
+            .map_err(|err| EmulatorError::Movie { path: path.clone(), err })?;
+        let movie_player = MoviePlayer::start(movie, &mut machine);
+        clock_rate = movie_player.clock_rate();
+        fps = movie_player.fps();
+        frames = movie_player.len();
+        player = Some(movie_player);
+    }
+    let mut recording = args.record_movie.as_ref().map(|_| Movie::record(&machine, clock_rate, fps));
+
+    let mut audio = match &args.audio {
+        Some(path) => {
+            let mut mixer = SoundMixer::new(args.config.sounds(SAMPLE_RATE), SAMPLE_RATE);
+            mixer.restore(machine.bus.port3, machine.bus.port5, machine.bus.cycles);
+            let writer = WavWriter::create(path, SAMPLE_RATE).map_err(|err| EmulatorError::Io { path: path.clone(), err })?;
+            Some((mixer, writer, path))
+        }
+        None => None,
+    };
+
+    let mut console = DebugConsole::new(args.debug);
+    for frame in 0..frames {
+        if let Some(player) = &mut player {
+            player.next_frame(&mut machine);
+        }
+        if let Some(movie) = &mut recording {
+            movie.record_frame(&machine);
+        }
+
+        if !console.run_frame(&mut machine, clock_rate, fps) {
+            break;
+        }
+        let sound_writes = machine.take_sound_writes();
+        if let Some((mixer, writer, path)) = &mut audio {
+            writer.write(&mixer.render(&sound_writes, machine.bus.cycles, clock_rate))
+                .map_err(|err| EmulatorError::Io { path: path.to_path_buf(), err })?;
+        }
+
+        for (address, value) in machine.bus.take_illegal_writes() {
+            eprintln!("frame {}: illegal write of {:02X} to {:04X}", frame, value, address);
+        }
+    }
+
+    if let (Some(movie), Some(path)) = (&recording, &args.record_movie) {
+        movie.save(path).map_err(|err| EmulatorError::Io { path: path.clone(), err })?;
+    }
+
+    let mut screen = ScreenDevice::new();
+    screen.flipped = args.config.cocktail() && machine.bus.flip_screen();
+    let frame = screen.render(&machine.bus.memory);
+    write_ppm(&args.screenshot, &frame).map_err(|err| EmulatorError::Io { path: args.screenshot.clone(), err })?;
+
+    if let Some(path) = &args.ram_dump {
+        std::fs::write(path, &machine.bus.memory[0x2000..0x4000]).map_err(|err| EmulatorError::Io { path: path.clone(), err })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("headless-{}-{}", std::process::id(), name))
+    }
+
+    fn args() -> Args {
+        Args { frames: 1, screenshot: temp_path("frame.ppm"), ..Args::default() }
+    }
+
+    #[test]
+    fn frames_are_dumped_to_the_screenshot_and_ram_dump() {
+        let mut args = args();
+        args.frames = 5;
+        args.screenshot = temp_path("run.ppm");
+        args.ram_dump = Some(temp_path("run.ram"));
+        run(&args).unwrap();
+
+        let mut machine = Machine::new();
+        machine.load_rom(&load_si_rom(&args.config.rom_dir()).unwrap());
+        for _ in 0..args.frames {
+            machine.run_frame(CLOCK_RATE, FPS);
+        }
+
+        let ppm = std::fs::read(&args.screenshot).unwrap();
+        let ram = std::fs::read(args.ram_dump.as_ref().unwrap()).unwrap();
+        std::fs::remove_file(&args.screenshot).unwrap();
+        std::fs::remove_file(args.ram_dump.as_ref().unwrap()).unwrap();
+
+        let header = b"P6\n224 256\n255\n";
+        assert!(ppm.starts_with(header));
+        assert_eq!(ppm.len(), header.len() + 224 * 256 * 3);
+        assert!(ram[..] == machine.bus.memory[0x2000..0x4000]);
+    }
+
+    #[test]
+    fn missing_rom_set_is_reported_with_its_path() {
+        let mut args = args();
+        let rom_dir = temp_path("no-such-rom-dir");
+        args.config.rom_dir = Some(rom_dir.clone());
+
+        let err = run(&args).err().unwrap();
+        assert!(matches!(err, EmulatorError::Rom { .. }));
+        assert!(err.to_string().contains(&rom_dir.display().to_string()));
+    }
+
+    #[test]
+    fn movie_of_another_rom_set_is_reported_with_its_path() {
+        let mut machine = Machine::new();
+        machine.load_rom(&[0x76]);
+        let path = temp_path("other-rom.mov");
+        Movie::record(&machine, CLOCK_RATE, FPS).save(&path).unwrap();
+
+        let mut args = args();
+        args.play_movie = Some(path.clone());
+        let err = run(&args).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(err, EmulatorError::Movie { .. }));
+        assert!(err.to_string().contains(&path.display().to_string()));
+        assert!(err.to_string().contains("ROM set"));
+    }
+
+    #[test]
+    fn unwritable_output_is_reported_with_its_path() {
+        let mut args = args();
+        args.screenshot = temp_path("no-such-dir").join("frame.ppm");
+
+        let err = run(&args).err().unwrap();
+        assert!(matches!(err, EmulatorError::Io { .. }));
+        assert!(err.to_string().contains(&args.screenshot.display().to_string()));
+    }
+}
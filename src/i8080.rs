@@ -1,3 +1,5 @@
+use crate::save_state::{StateError, StateReader};
+
 const INSTR_CYCLES: [u8; 0x100] = [
     4, 10, 7,  5, 5,  5,  7,  4, 4, 10, 7,  5, 5, 5, 7, 4, //0x0...
     4, 10, 7,  5, 5,  5,  7,  4, 4, 10, 7,  5, 5, 5, 7, 4, //0x1...
@@ -14,12 +16,64 @@ const INSTR_CYCLES: [u8; 0x100] = [
     4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, //0xA...
     4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, //0xB...
 
-    11, 10, 10, 10, 17, 11, 7, 11, 11, 10, 10, 10, 10, 17, 7, 11, //0xC...
-    11, 10, 10, 10, 17, 11, 7, 11, 11, 10, 10, 10, 10, 17, 7, 11, //0xD...
-    11, 10, 10, 18, 17, 11, 7, 11, 11, 5,  10, 5,  17, 17, 7, 11, //0xE...
-    11, 10, 10, 4,  17, 11, 7, 11, 11, 5,  10, 4,  17, 17, 7, 11, //0xF...
+    5, 10, 10, 10, 11, 11, 7, 11, 5, 10, 10, 10, 11, 17, 7, 11, //0xC...
+    5, 10, 10, 10, 11, 11, 7, 11, 5, 10, 10, 10, 11, 17, 7, 11, //0xD...
+    5, 10, 10, 18, 11, 11, 7, 11, 5, 5,  10, 5,  11, 17, 7, 11, //0xE...
+    5, 10, 10, 4,  11, 11, 7, 11, 5, 5,  10, 4,  11, 17, 7, 11, //0xF...
 ];
 
+// Conditional calls and returns take the table count when the condition fails
+// and this many cycles more when they branch (CALL 11/17, RET 5/11)
+const CONDITION_MET_EXTRA_CYCLES: usize = 6;
+
+
+/// Everything the CPU talks to: memory and the IN/OUT port space.
+/// Each machine (the Space Invaders board, the CP/M harness) provides its own map.
+pub trait Bus {
+    fn read_memory(&self, address: usize) -> u8;
+    fn write_memory(&mut self, address: usize, value: u8);
+    fn read_port(&mut self, port: u8) -> u8;
+    fn write_port(&mut self, port: u8, value: u8);
+}
+
+/// 64 KiB of plain RAM with nothing attached to the I/O ports.
+pub struct Ram {
+    pub memory: [u8; 0x10000],
+}
+
+impl Ram {
+    pub fn new() -> Self {
+        Ram { memory: [0; 0x10000] }
+    }
+
+    pub fn load(&mut self, data: &[u8], offset: usize) {
+        self.memory[offset..offset + data.len()].copy_from_slice(data);
+    }
+}
+
+impl Default for Ram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bus for Ram {
+    // addresses past 0xFFFF wrap around like the 16 address lines do
+    fn read_memory(&self, address: usize) -> u8 {
+        self.memory[address & 0xFFFF]
+    }
+
+    fn write_memory(&mut self, address: usize, value: u8) {
+        self.memory[address & 0xFFFF] = value;
+    }
+
+    fn read_port(&mut self, _port: u8) -> u8 {
+        0
+    }
+
+    fn write_port(&mut self, _port: u8, _value: u8) {}
+}
+
 
 #[derive(Clone, Copy)]
 enum FlagBit {
@@ -30,30 +84,30 @@ enum FlagBit {
     Sign = 7,
 }
 
+#[derive(Clone)]
 pub struct I8080 {
-    pub memory: [u8; 0x10000],
-
     pub a: u8,
-    b: u8,
-    c: u8,
-    d: u8,
-    e: u8,
-    h: u8,
-    l: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
 
-    flags: u8, // S, Z, -, AC, -, P, -, C
+    flags: u8, // S, Z, 0, AC, 0, P, 1, C
     pub sp: usize,
     pub pc: usize,
 
     pub enable_interrupts: bool,
     pub halted: bool,
+    interrupt_delay: bool, // EI only takes effect after the instruction that follows it
+    pending_interrupt: Option<usize>,
 }
 
 impl I8080 {
 
     pub fn new() -> Self {
         I8080 {
-            memory: [0; 0x10000],
             a: 0,
             b: 0,
             c: 0,
@@ -62,11 +116,13 @@ impl I8080 {
             h: 0,
             l: 0,
 
-            flags: 0,
+            flags: 0b0000_0010,
             sp: 0xFFFF,
             pc: 0,
             enable_interrupts: false,
             halted: false,
+            interrupt_delay: false,
+            pending_interrupt: None,
         }
     }
 
@@ -88,7 +144,41 @@ impl I8080 {
         (high as usize) << 8 | low as usize
     }
 
-    pub fn print_state(&self) {
+    pub(crate) fn write_state(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[self.a, self.b, self.c, self.d, self.e, self.h, self.l, self.flags]);
+        out.extend_from_slice(&(self.sp as u16).to_le_bytes());
+        out.extend_from_slice(&(self.pc as u16).to_le_bytes());
+        out.extend_from_slice(&[
+            self.enable_interrupts as u8,
+            self.halted as u8,
+            self.interrupt_delay as u8,
+            self.pending_interrupt.map_or(0xFF, |int_num| int_num as u8),
+        ]);
+    }
+
+    pub(crate) fn read_state(reader: &mut StateReader) -> Result<Self, StateError> {
+        Ok(I8080 {
+            a: reader.u8()?,
+            b: reader.u8()?,
+            c: reader.u8()?,
+            d: reader.u8()?,
+            e: reader.u8()?,
+            h: reader.u8()?,
+            l: reader.u8()?,
+            flags: reader.u8()?,
+            sp: reader.u16()? as usize,
+            pc: reader.u16()? as usize,
+            enable_interrupts: reader.bool()?,
+            halted: reader.bool()?,
+            interrupt_delay: reader.bool()?,
+            pending_interrupt: match reader.u8()? {
+                0xFF => None,
+                int_num => Some(int_num as usize),
+            },
+        })
+    }
+
+    pub fn print_state(&self, bus: &impl Bus) {
         println!(" --- \ni8080 state: ");
         println!(
             "\tA: {:x}, B: {:x}, C: {:x}, D: {:x}, E: {:x}, H: {:x}, L: {:x}",
@@ -96,39 +186,53 @@ impl I8080 {
         );
 
         println!("\tPC: {:x}, SP: {:x}, EI: {}; INSTR: {:x}\n\tFlags = Zero: {}, Sign: {}, Parity: {}, Carry: {}, AuxCarry: {}",
-            self.pc, self.sp, self.enable_interrupts, self.read_memory(self.pc),
+            self.pc, self.sp, self.enable_interrupts, bus.read_memory(self.pc),
             self.get_flag_bit(FlagBit::Zero) as u8, self.get_flag_bit(FlagBit::Sign)  as u8,
             self.get_flag_bit(FlagBit::Parity) as u8, self.get_flag_bit(FlagBit::Carry) as u8,
             self.get_flag_bit(FlagBit::AuxiliaryCarry) as u8);
     }
 
-    pub fn load_rom(&mut self, rom: &Vec<u8>, offset: usize){
-        for (byte_num, byte) in rom.iter().enumerate() {
-            self.memory[offset + byte_num] = *byte;
+    pub fn read_instr(&self, bus: &impl Bus) -> Option<u8> {
+        if self.pc > 0xFFFF {
+            return None;
         }
+        Some(bus.read_memory(self.pc))
     }
 
-    pub fn read_instr(&self) -> Option<u8> {
-        self.memory.get(self.pc).copied()
+    /// What the RESET pin does: PC back to 0, interrupts disabled and the CPU out of HLT.
+    /// Registers, flags and SP keep their values like on the real chip.
+    pub fn reset(&mut self) {
+        self.pc = 0;
+        self.enable_interrupts = false;
+        self.halted = false;
+        self.interrupt_delay = false;
+        self.pending_interrupt = None;
     }
 
-    pub fn generate_interrupt(&mut self, int_num: usize) {
-        if self.enable_interrupts {
-            self.push(self.pc);
+    /// Requests RST `int_num`. Right after EI the request is held until the next
+    /// instruction completes, a halted CPU wakes up and continues after the HLT.
+    pub fn generate_interrupt(&mut self, bus: &mut impl Bus, int_num: usize) {
+        if self.enable_interrupts && self.interrupt_delay {
+            self.pending_interrupt = Some(int_num);
+        } else if self.enable_interrupts {
+            self.halted = false;
+            self.push(bus, self.pc);
             self.pc = 0x8 * int_num;
             self.enable_interrupts = false;
         }
     }
 
-    pub fn push(&mut self, data: usize) {
-        self.write_memory(self.sp - 2, (data & 0xFF) as u8);
-        self.write_memory(self.sp - 1, ((data >> 8) & 0xFF) as u8);
-        self.sp -= 2;
+    // SP wraps around the 64 KiB address space like on the real chip
+    pub fn push(&mut self, bus: &mut impl Bus, data: usize) {
+        self.sp = self.sp.wrapping_sub(2) & 0xFFFF;
+        bus.write_memory(self.sp, (data & 0xFF) as u8);
+        bus.write_memory((self.sp + 1) & 0xFFFF, ((data >> 8) & 0xFF) as u8);
     }
 
-    pub fn pop(&mut self) -> usize {
-        self.sp += 2;
-        Self::join_bytes(self.read_memory(self.sp - 1), self.read_memory(self.sp - 2))
+    pub fn pop(&mut self, bus: &impl Bus) -> usize {
+        let data = Self::join_bytes(bus.read_memory((self.sp + 1) & 0xFFFF), bus.read_memory(self.sp));
+        self.sp = (self.sp + 2) & 0xFFFF;
+        data
     }
 
     fn add_with_carry(&mut self, addend: u8){
@@ -147,7 +251,8 @@ impl I8080 {
         let (sub_one, is_overflow2) = sub_data.overflowing_sub(carry_bit);
         self.set_zsp_flags(sub_one);
         self.set_flag_bit(FlagBit::Carry, is_overflow1 || is_overflow2);
-        self.set_flag_bit(FlagBit::AuxiliaryCarry, self.a & 0xF < (subtrahend & 0xF) + carry_bit);
+        // the 8080 subtracts by adding the complement, AC is the carry out of bit 3 of that addition
+        self.set_flag_bit(FlagBit::AuxiliaryCarry, (self.a & 0xF) + (!subtrahend & 0xF) + (1 - carry_bit) > 0xF);
         self.a = sub_one;
     }
 
@@ -163,15 +268,16 @@ impl I8080 {
         let (sub_data, is_overflow) = self.a.overflowing_sub(subtrahend);
         self.set_zsp_flags(sub_data);
         self.set_flag_bit(FlagBit::Carry, is_overflow);
-        self.set_flag_bit(FlagBit::AuxiliaryCarry, self.a & 0xF < subtrahend & 0xF);
+        self.set_flag_bit(FlagBit::AuxiliaryCarry, (self.a & 0xF) + (!subtrahend & 0xF) + 1 > 0xF);
         self.a = sub_data;
     }
 
     fn and_logical(&mut self, rhs: u8){
+        // ANA sets AC from bit 3 of the operands, unlike ORA and XRA
+        self.set_flag_bit(FlagBit::AuxiliaryCarry, (self.a | rhs) & 0x8 != 0);
         self.a &= rhs;
         self.set_zsp_flags(self.a);
         self.set_flag_bit(FlagBit::Carry, false);
-        self.set_flag_bit(FlagBit::AuxiliaryCarry, false);
     }
 
     fn xor_logical(&mut self, rhs: u8){
@@ -192,30 +298,24 @@ impl I8080 {
         let (result, is_overflow) = self.a.overflowing_sub(rhs);
         self.set_zsp_flags(result);
         self.set_flag_bit(FlagBit::Carry, is_overflow);
-        self.set_flag_bit(FlagBit::AuxiliaryCarry, self.a & 0xF < rhs & 0xF);
-    }
-
-    pub fn read_memory(&self, address: usize) -> u8 {
-        self.memory[address]
-    }
-
-    pub fn write_memory(&mut self, address: usize, value: u8){
-        self.memory[address] = value;
+        self.set_flag_bit(FlagBit::AuxiliaryCarry, (self.a & 0xF) + (!rhs & 0xF) + 1 > 0xF);
     }
 
-    pub fn execute(&mut self, instr: u8) -> usize {
+    pub fn execute(&mut self, bus: &mut impl Bus, instr: u8) -> usize {
+        let mut cycles = INSTR_CYCLES[instr as usize] as usize;
+        self.interrupt_delay = false;
         match instr {
-            0x00 => {
+            0x00 | 0x08 | 0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 => {
                 self.pc += 1;
-            } // NOP
+            } // NOP, the rest are undocumented aliases
             0x01 => {
-                self.b = self.read_memory(self.pc + 2);
-                self.c = self.read_memory(self.pc + 1);
+                self.b = bus.read_memory(self.pc + 2);
+                self.c = bus.read_memory(self.pc + 1);
                 self.pc += 3;
             } // LXI B,D16
             0x02 => {
                 let mem_addr = Self::join_bytes(self.b, self.c);
-                self.write_memory(mem_addr, self.a);
+                bus.write_memory(mem_addr, self.a);
                 self.pc += 1;
             } // STAX B
             0x03 => {
@@ -227,17 +327,17 @@ impl I8080 {
             0x04 => {
                 self.b = self.b.wrapping_add(1);
                 self.set_zsp_flags(self.b);
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.b == 0x10);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.b & 0xF == 0);
                 self.pc += 1;
             } // INR B
             0x05 => {
                 self.b = self.b.wrapping_sub(1);
                 self.set_zsp_flags(self.b);
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.b & 0xF == 0xF);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.b & 0xF != 0xF);
                 self.pc += 1;
             } // DCR B
             0x06 => {
-                self.b = self.read_memory(self.pc + 1);
+                self.b = bus.read_memory(self.pc + 1);
                 self.pc += 2;
             } // MVI B, D8
             0x07 => {
@@ -257,7 +357,7 @@ impl I8080 {
             } // DAD B
             0x0a => {
                 let mem_addr = Self::join_bytes(self.b, self.c);
-                self.a = self.read_memory(mem_addr);
+                self.a = bus.read_memory(mem_addr);
                 self.pc += 1;
             } // LDAX B
             0x0b => {
@@ -269,17 +369,17 @@ impl I8080 {
             0x0c => {
                 self.c = self.c.wrapping_add(1);
                 self.set_zsp_flags(self.c);
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.c == 0x10);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.c & 0xF == 0);
                 self.pc += 1;
             } // INR C
             0x0d => {
                 self.c = self.c.wrapping_sub(1);
                 self.set_zsp_flags(self.c);
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.c & 0xF == 0xF);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.c & 0xF != 0xF);
                 self.pc += 1;
             } // DCR C
             0x0e => {
-                self.c = self.read_memory(self.pc + 1);
+                self.c = bus.read_memory(self.pc + 1);
                 self.pc += 2;
             } // MVI C,D8
             0x0f => {
@@ -288,13 +388,13 @@ impl I8080 {
                 self.pc += 1;
             } // RRC
             0x11 => {
-                self.d = self.read_memory(self.pc + 2);
-                self.e = self.read_memory(self.pc + 1);
+                self.d = bus.read_memory(self.pc + 2);
+                self.e = bus.read_memory(self.pc + 1);
                 self.pc += 3;
             } // LXI D,D16
             0x12 => {
                 let mem_addr = Self::join_bytes(self.d, self.e);
-                self.write_memory(mem_addr, self.a);
+                bus.write_memory(mem_addr, self.a);
                 self.pc += 1;
             } // STAX D
             0x13 => {
@@ -306,17 +406,17 @@ impl I8080 {
             0x14 => {
                 self.d = self.d.wrapping_add(1);
                 self.set_zsp_flags(self.d);
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.d == 0x10);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.d & 0xF == 0);
                 self.pc += 1;
             } // INR D
             0x15 => {
                 self.d = self.d.wrapping_sub(1);
                 self.set_zsp_flags(self.d);
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.d & 0xF == 0xF);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.d & 0xF != 0xF);
                 self.pc += 1;
             } // DCR D
             0x16 => {
-                self.d = self.read_memory(self.pc + 1);
+                self.d = bus.read_memory(self.pc + 1);
                 self.pc += 2;
             } // MVI D, D8
             0x17 => {
@@ -337,7 +437,7 @@ impl I8080 {
             } // DAD D
             0x1a => {
                 let mem_addr = Self::join_bytes(self.d, self.e);
-                self.a = self.read_memory(mem_addr);
+                self.a = bus.read_memory(mem_addr);
                 self.pc += 1;
             } // LDAX D
             0x1b => {
@@ -349,17 +449,17 @@ impl I8080 {
             0x1c => {
                 self.e = self.e.wrapping_add(1);
                 self.set_zsp_flags(self.e);
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.e == 0x10);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.e & 0xF == 0);
                 self.pc += 1;
             } // INR E
             0x1d => {
                 self.e = self.e.wrapping_sub(1);
                 self.set_zsp_flags(self.e);
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.e & 0xF == 0xF);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.e & 0xF != 0xF);
                 self.pc += 1;
             } // DCR E
             0x1e => {
-                self.e = self.read_memory(self.pc + 1);
+                self.e = bus.read_memory(self.pc + 1);
                 self.pc += 2;
             } // MVI E,D8
             0x1f => {
@@ -368,18 +468,15 @@ impl I8080 {
                 self.a = self.a.rotate_right(1) & !(0x1 << 7) | (carry_bit << 7);
                 self.pc += 1;
             } // RAR
-            0x20 => {
-                self.pc += 1;
-            } // RIM // NOPE
             0x21 => {
-                self.h = self.read_memory(self.pc + 2);
-                self.l = self.read_memory(self.pc + 1);
+                self.h = bus.read_memory(self.pc + 2);
+                self.l = bus.read_memory(self.pc + 1);
                 self.pc += 3;
             } // LXI H,D16
             0x22 => {
-                let mem_addr = Self::join_bytes(self.read_memory(self.pc + 2), self.read_memory(self.pc + 1));
-                self.write_memory(mem_addr, self.l);
-                self.write_memory(mem_addr + 1, self.h);
+                let mem_addr = Self::join_bytes(bus.read_memory(self.pc + 2), bus.read_memory(self.pc + 1));
+                bus.write_memory(mem_addr, self.l);
+                bus.write_memory(mem_addr + 1, self.h);
                 self.pc += 3;
             } // SHLD adr
             0x23 => {
@@ -391,30 +488,33 @@ impl I8080 {
             0x24 => {
                 self.h = self.h.wrapping_add(1);
                 self.set_zsp_flags(self.h);
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.h == 0x10);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.h & 0xF == 0);
                 self.pc += 1;
             } // INR H
             0x25 => {
                 self.h = self.h.wrapping_sub(1);
                 self.set_zsp_flags(self.h);
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.h & 0xF == 0xF);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.h & 0xF != 0xF);
                 self.pc += 1;
             } // DCR H
             0x26 => {
-                self.h = self.read_memory(self.pc + 1);
+                self.h = bus.read_memory(self.pc + 1);
                 self.pc += 2;
             } // MVI H,D8
             0x27 => {
-                if self.a & 0xF > 0x9 || self.get_flag_bit(FlagBit::AuxiliaryCarry) {
-                    self.set_flag_bit(FlagBit::AuxiliaryCarry, true);
-                    self.set_flag_bit(FlagBit::Carry, (self.a >> 4) & 0xF > 0x9 || self.get_flag_bit(FlagBit::Carry));
-                    self.a = self.a.wrapping_add(0x6);
+                let low_nibble = self.a & 0xF;
+                let high_nibble = self.a >> 4;
+                let mut correction = 0;
+                let mut carry = self.get_flag_bit(FlagBit::Carry);
+                if low_nibble > 0x9 || self.get_flag_bit(FlagBit::AuxiliaryCarry) {
+                    correction |= 0x06;
                 }
-                if (self.a >> 4) & 0xF > 0x9 || self.get_flag_bit(FlagBit::Carry) {
-                    self.set_flag_bit(FlagBit::Carry, true);
-                    self.a = self.a.wrapping_add(0x60);
+                if high_nibble > 0x9 || carry || (high_nibble >= 0x9 && low_nibble > 0x9) {
+                    correction |= 0x60;
+                    carry = true;
                 }
-                self.set_zsp_flags(self.a);
+                self.add(correction);
+                self.set_flag_bit(FlagBit::Carry, carry);
                 self.pc += 1;
             } // DAA // DECIMAL ADJUST ACCUMULATOR
             0x29 => {
@@ -427,9 +527,9 @@ impl I8080 {
                 self.pc += 1;
             } // DAD H
             0x2a => {
-                let mem_addr = Self::join_bytes(self.read_memory(self.pc + 2), self.read_memory(self.pc + 1));
-                self.l = self.read_memory(mem_addr);
-                self.h = self.read_memory(mem_addr + 1);
+                let mem_addr = Self::join_bytes(bus.read_memory(self.pc + 2), bus.read_memory(self.pc + 1));
+                self.l = bus.read_memory(mem_addr);
+                self.h = bus.read_memory(mem_addr + 1);
                 self.pc += 3;
             } // LHLD adr
             0x2b => {
@@ -441,33 +541,30 @@ impl I8080 {
             0x2c => {
                 self.l = self.l.wrapping_add(1);
                 self.set_zsp_flags(self.l);
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.l == 0x10);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.l & 0xF == 0);
                 self.pc += 1;
             } // INR L
             0x2d => {
                 self.l = self.l.wrapping_sub(1);
                 self.set_zsp_flags(self.l);
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.l & 0xF == 0xF);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.l & 0xF != 0xF);
                 self.pc += 1;
             } // DCR L
             0x2e => {
-                self.l = self.read_memory(self.pc + 1);
+                self.l = bus.read_memory(self.pc + 1);
                 self.pc += 2;
             } // MVI L, D8
             0x2f => {
                 self.a = !self.a;
                 self.pc += 1;
             } // CMA
-            0x30 => {
-                self.pc += 1;
-            } // SIM // NOPE
             0x31 => {
-                self.sp = Self::join_bytes(self.read_memory(self.pc + 2), self.read_memory(self.pc + 1));
+                self.sp = Self::join_bytes(bus.read_memory(self.pc + 2), bus.read_memory(self.pc + 1));
                 self.pc += 3;
             } // LXI SP, D16
             0x32 => {
-                let mem_addr = Self::join_bytes(self.read_memory(self.pc + 2), self.read_memory(self.pc + 1));
-                self.write_memory(mem_addr, self.a);
+                let mem_addr = Self::join_bytes(bus.read_memory(self.pc + 2), bus.read_memory(self.pc + 1));
+                bus.write_memory(mem_addr, self.a);
                 self.pc += 3;
             } // STA adr
             0x33 => {
@@ -476,21 +573,24 @@ impl I8080 {
             } // INX SP
             0x34 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.write_memory(mem_addr, self.read_memory(mem_addr).wrapping_add(1));
-                self.set_zsp_flags(self.read_memory(mem_addr));
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.read_memory(mem_addr) == 0x10);
+                // flags come from the result, reading it back would see ROM or open bus unchanged
+                let value = bus.read_memory(mem_addr).wrapping_add(1);
+                bus.write_memory(mem_addr, value);
+                self.set_zsp_flags(value);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, value & 0xF == 0);
                 self.pc += 1;
             } // INR M
             0x35 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.write_memory(mem_addr, self.read_memory(mem_addr).wrapping_sub(1));
-                self.set_zsp_flags(self.read_memory(mem_addr));
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.read_memory(mem_addr) & 0xF == 0xF);
+                let value = bus.read_memory(mem_addr).wrapping_sub(1);
+                bus.write_memory(mem_addr, value);
+                self.set_zsp_flags(value);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, value & 0xF != 0xF);
                 self.pc += 1;
             } // DCR M
             0x36 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.write_memory(mem_addr, self.read_memory(self.pc + 1));
+                bus.write_memory(mem_addr, bus.read_memory(self.pc + 1));
                 self.pc += 2;
             } // MVI M,D8
             0x37 => {
@@ -507,8 +607,8 @@ impl I8080 {
                 self.pc += 1;
             } // DAD SP
             0x3a => {
-                let mem_addr = Self::join_bytes(self.read_memory(self.pc + 2), self.read_memory(self.pc + 1));
-                self.a = self.read_memory(mem_addr);
+                let mem_addr = Self::join_bytes(bus.read_memory(self.pc + 2), bus.read_memory(self.pc + 1));
+                self.a = bus.read_memory(mem_addr);
                 self.pc += 3;
             } // LDA adr
             0x3b => {
@@ -518,17 +618,17 @@ impl I8080 {
             0x3c => {
                 self.a = self.a.wrapping_add(1);
                 self.set_zsp_flags(self.a);
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.a == 0x10);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.a & 0xF == 0);
                 self.pc += 1;
             } // INR A
             0x3d => {
                 self.a = self.a.wrapping_sub(1);
                 self.set_zsp_flags(self.a);
-                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.a & 0xF == 0xF);
+                self.set_flag_bit(FlagBit::AuxiliaryCarry, self.a & 0xF != 0xF);
                 self.pc += 1;
             } // DCR A
             0x3e => {
-                self.a = self.read_memory(self.pc + 1);
+                self.a = bus.read_memory(self.pc + 1);
                 self.pc += 2;
             } // MVI A,D8
             0x3f => {
@@ -536,7 +636,6 @@ impl I8080 {
                 self.pc += 1;
             } // CMC
             0x40 => {
-                self.b = self.b;
                 self.pc += 1;
             } // MOV B,B
             0x41 => {
@@ -561,7 +660,7 @@ impl I8080 {
             } // MOV B,L
             0x46 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.b = self.read_memory(mem_addr);
+                self.b = bus.read_memory(mem_addr);
                 self.pc += 1;
             } // MOV B,M
             0x47 => {
@@ -573,7 +672,6 @@ impl I8080 {
                 self.pc += 1;
             } // MOV C,B
             0x49 => {
-                self.c = self.c;
                 self.pc += 1;
             } // MOV C,C
             0x4a => {
@@ -594,7 +692,7 @@ impl I8080 {
             } // MOV C,L
             0x4e => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.c = self.read_memory(mem_addr);
+                self.c = bus.read_memory(mem_addr);
                 self.pc += 1;
             } // MOV C,M
             0x4f => {
@@ -610,7 +708,6 @@ impl I8080 {
                 self.pc += 1;
             } // MOV D,C
             0x52 => {
-                self.d = self.d;
                 self.pc += 1;
             } // MOV D,D
             0x53 => {
@@ -627,7 +724,7 @@ impl I8080 {
             } // MOV D,L
             0x56 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.d = self.read_memory(mem_addr);
+                self.d = bus.read_memory(mem_addr);
                 self.pc += 1;
             } // MOV D,M
             0x57 => {
@@ -647,7 +744,6 @@ impl I8080 {
                 self.pc += 1;
             } // MOV E,D
             0x5b => {
-                self.e = self.e;
                 self.pc += 1;
             } // MOV E,E
             0x5c => {
@@ -660,7 +756,7 @@ impl I8080 {
             } // MOV E,L
             0x5e => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.e = self.read_memory(mem_addr);
+                self.e = bus.read_memory(mem_addr);
                 self.pc += 1;
             } // MOV E,M
             0x5f => {
@@ -684,7 +780,6 @@ impl I8080 {
                 self.pc += 1;
             } // MOV H,E
             0x64 => {
-                self.h = self.h;
                 self.pc += 1;
             } // MOV H,H
             0x65 => {
@@ -693,7 +788,7 @@ impl I8080 {
             } // MOV H,L
             0x66 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.h = self.read_memory(mem_addr);
+                self.h = bus.read_memory(mem_addr);
                 self.pc += 1;
             } // MOV H,M
             0x67 => {
@@ -721,12 +816,11 @@ impl I8080 {
                 self.pc += 1;
             } // MOV L,H
             0x6d => {
-                self.l = self.l;
                 self.pc += 1;
             } // MOV L,L
             0x6e => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.l = self.read_memory(mem_addr);
+                self.l = bus.read_memory(mem_addr);
                 self.pc += 1;
             } // MOV L,M
             0x6f => {
@@ -735,32 +829,32 @@ impl I8080 {
             } // MOV L,A
             0x70 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.write_memory(mem_addr, self.b);
+                bus.write_memory(mem_addr, self.b);
                 self.pc += 1;
             } // MOV M,B
             0x71 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.write_memory(mem_addr, self.c);
+                bus.write_memory(mem_addr, self.c);
                 self.pc += 1;
             } // MOV M,C
             0x72 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.write_memory(mem_addr, self.d);
+                bus.write_memory(mem_addr, self.d);
                 self.pc += 1;
             } // MOV M,D
             0x73 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.write_memory(mem_addr, self.e);
+                bus.write_memory(mem_addr, self.e);
                 self.pc += 1;
             } // MOV M,E
             0x74 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.write_memory(mem_addr, self.h);
+                bus.write_memory(mem_addr, self.h);
                 self.pc += 1;
             } // MOV M,H
             0x75 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.write_memory(mem_addr, self.l);
+                bus.write_memory(mem_addr, self.l);
                 self.pc += 1;
             } // MOV M,L
             0x76 => {
@@ -769,7 +863,7 @@ impl I8080 {
             } // HLT
             0x77 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.write_memory(mem_addr, self.a);
+                bus.write_memory(mem_addr, self.a);
                 self.pc += 1;
             } // MOV M,A
             0x78 => {
@@ -798,11 +892,10 @@ impl I8080 {
             } // MOV A,L
             0x7e => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.a = self.read_memory(mem_addr);
+                self.a = bus.read_memory(mem_addr);
                 self.pc += 1;
             } // MOV A,M
             0x7f => {
-                self.a = self.a;
                 self.pc += 1;
             } // MOV A,A
             0x80 => {
@@ -831,7 +924,7 @@ impl I8080 {
             } // ADD L
             0x86 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.add(self.read_memory(mem_addr));
+                self.add(bus.read_memory(mem_addr));
                 self.pc += 1;
             } // ADD M
             0x87 => {
@@ -864,7 +957,7 @@ impl I8080 {
             } // ADC L
             0x8e => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.add_with_carry(self.read_memory(mem_addr));
+                self.add_with_carry(bus.read_memory(mem_addr));
                 self.pc += 1;
             } // ADC M
             0x8f => {
@@ -897,7 +990,7 @@ impl I8080 {
             } // SUB L
             0x96 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.sub(self.read_memory(mem_addr));
+                self.sub(bus.read_memory(mem_addr));
                 self.pc += 1;
             } // SUB M
             0x97 => {
@@ -930,7 +1023,7 @@ impl I8080 {
             } // SBB L
             0x9e => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.sub_with_borrow(self.read_memory(mem_addr));
+                self.sub_with_borrow(bus.read_memory(mem_addr));
                 self.pc += 1;
             } // SBB M
             0x9f => {
@@ -963,7 +1056,7 @@ impl I8080 {
             } // ANA L
             0xa6 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.and_logical(self.read_memory(mem_addr));
+                self.and_logical(bus.read_memory(mem_addr));
                 self.pc += 1;
             } // ANA M
             0xa7 => {
@@ -996,7 +1089,7 @@ impl I8080 {
             } // XRA L
             0xae => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.xor_logical(self.read_memory(mem_addr));
+                self.xor_logical(bus.read_memory(mem_addr));
                 self.pc += 1;
             } // XRA M
             0xaf => {
@@ -1029,7 +1122,7 @@ impl I8080 {
             } // ORA L
             0xb6 => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.or_logical(self.read_memory(mem_addr));
+                self.or_logical(bus.read_memory(mem_addr));
                 self.pc += 1;
             } // ORA M
             0xb7 => {
@@ -1062,7 +1155,7 @@ impl I8080 {
             } // CMP L
             0xbe => {
                 let mem_addr = Self::join_bytes(self.h, self.l);
-                self.compare(self.read_memory(mem_addr));
+                self.compare(bus.read_memory(mem_addr));
                 self.pc += 1;
             } // CMP M
             0xbf => {
@@ -1071,210 +1164,224 @@ impl I8080 {
             } // CMP A
             0xc0 => {
                 if !self.get_flag_bit(FlagBit::Zero) {
-                    self.pc = self.pop();
+                    self.pc = self.pop(bus);
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 1;
                 }
             } // RNZ
             0xc1 => {
-                let bc = self.pop();
+                let bc = self.pop(bus);
                 self.b = ((bc >> 8) & 0xFF) as u8;
                 self.c = (bc & 0xFF) as u8;
                 self.pc += 1;
             } // POP B
             0xc2 => {
                 if !self.get_flag_bit(FlagBit::Zero) {
-                    self.pc = Self::join_bytes(self.read_memory(self.pc + 2), self.read_memory(self.pc + 1));
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc + 2), bus.read_memory(self.pc + 1));
                 } else {
                     self.pc += 3;
                 }
             } // JNZ adr
-            0xc3 => {
-                self.pc = Self::join_bytes(self.read_memory(self.pc + 2), self.read_memory(self.pc + 1));
-            }, // JMP adr
+            0xc3 | 0xcb => {
+                self.pc = Self::join_bytes(bus.read_memory(self.pc + 2), bus.read_memory(self.pc + 1));
+            }, // JMP adr, 0xcb is an undocumented alias
             0xc4 => {
                 if !self.get_flag_bit(FlagBit::Zero) {
-                    self.push(self.pc+3);
-                    self.pc = Self::join_bytes(self.read_memory(self.pc+2), self.read_memory(self.pc+1));
+                    self.push(bus, self.pc+3);
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc+2), bus.read_memory(self.pc+1));
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 3;
                 }
             } // CNZ adr
             0xc5 => {
-                self.push(Self::join_bytes(self.b, self.c));
+                self.push(bus, Self::join_bytes(self.b, self.c));
                 self.pc += 1;
             } // PUSH B
             0xc6 => {
-                self.add(self.read_memory(self.pc+1));
+                self.add(bus.read_memory(self.pc+1));
                 self.pc += 2;
             } // ADI D8
             0xc7 => {
-                self.push(self.pc + 1);
+                self.push(bus, self.pc + 1);
                 self.pc = 0;
             }   // RST 0
             0xc8 => {
                 if self.get_flag_bit(FlagBit::Zero) {
-                    self.pc = self.pop();
+                    self.pc = self.pop(bus);
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 1;
                 }
             } // RZ
-            0xc9 => {
-                self.pc = self.pop();
-            } // RET
+            0xc9 | 0xd9 => {
+                self.pc = self.pop(bus);
+            } // RET, 0xd9 is an undocumented alias
             0xca => {
                 if self.get_flag_bit(FlagBit::Zero) {
-                    self.pc = Self::join_bytes(self.read_memory(self.pc + 2), self.read_memory(self.pc + 1));
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc + 2), bus.read_memory(self.pc + 1));
                 } else {
                     self.pc += 3;
                 }
             } // JZ adr
             0xcc => {
                 if self.get_flag_bit(FlagBit::Zero) {
-                    self.push(self.pc+3);
-                    self.pc = Self::join_bytes(self.read_memory(self.pc+2), self.read_memory(self.pc+1));
+                    self.push(bus, self.pc+3);
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc+2), bus.read_memory(self.pc+1));
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 3;
                 }
             } // CZ adr
-            0xcd => {
-                self.push(self.pc+3);
-                self.pc = Self::join_bytes(self.read_memory(self.pc+2), self.read_memory(self.pc+1));
-            } // CALL adr
+            0xcd | 0xdd | 0xed | 0xfd => {
+                self.push(bus, self.pc+3);
+                self.pc = Self::join_bytes(bus.read_memory(self.pc+2), bus.read_memory(self.pc+1));
+            } // CALL adr, the rest are undocumented aliases
             0xce => {
-                self.add_with_carry(self.read_memory(self.pc+1));
+                self.add_with_carry(bus.read_memory(self.pc+1));
                 self.pc += 2;
             } // ACI D8 // A <- A + data + CY
             0xcf => {
-                self.push(self.pc + 1);
-                self.pc = 1 * 0x8;
+                self.push(bus, self.pc + 1);
+                self.pc = 0x8;
             }   // RST 1
             0xd0 => {
                 if !self.get_flag_bit(FlagBit::Carry) {
-                    self.pc = self.pop();
+                    self.pc = self.pop(bus);
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 1;
                 }
             } // RNC
             0xd1 => {
-                let de = self.pop();
+                let de = self.pop(bus);
                 self.d = ((de >> 8) & 0xFF) as u8;
                 self.e = (de & 0xFF) as u8;
                 self.pc += 1;
             } // POP D
             0xd2 => {
                 if !self.get_flag_bit(FlagBit::Carry) {
-                    self.pc = Self::join_bytes(self.read_memory(self.pc + 2), self.read_memory(self.pc + 1));
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc + 2), bus.read_memory(self.pc + 1));
                 } else {
                     self.pc += 3;
                 }
             } // JNC adr
             0xd3 => {
-                // self.write_port(self.read_memory(self.pc + 1), self.a);
+                bus.write_port(bus.read_memory(self.pc + 1), self.a);
                 self.pc += 2;
             } // OUT D8
             0xd4 => {
                 if !self.get_flag_bit(FlagBit::Carry) {
-                    self.push(self.pc+3);
-                    self.pc = Self::join_bytes(self.read_memory(self.pc+2), self.read_memory(self.pc+1));
+                    self.push(bus, self.pc+3);
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc+2), bus.read_memory(self.pc+1));
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 3;
                 }
             } // CNC adr
             0xd5 => {
-                self.push(Self::join_bytes(self.d, self.e));
+                self.push(bus, Self::join_bytes(self.d, self.e));
                 self.pc += 1;
             } // PUSH D
             0xd6 => {
-                self.sub(self.read_memory(self.pc+1));
+                self.sub(bus.read_memory(self.pc+1));
                 self.pc += 2;
             } // SUI D8
             0xd7 => {
-                self.push(self.pc + 1);
+                self.push(bus, self.pc + 1);
                 self.pc = 2 * 0x8;
             } // RST 2
             0xd8 => {
                 if self.get_flag_bit(FlagBit::Carry) {
-                    self.pc = self.pop();
+                    self.pc = self.pop(bus);
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 1;
                 }
             }   // RC
             0xda => {
                 if self.get_flag_bit(FlagBit::Carry) {
-                    self.pc = Self::join_bytes(self.read_memory(self.pc + 2), self.read_memory(self.pc + 1));
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc + 2), bus.read_memory(self.pc + 1));
                 } else {
                     self.pc += 3;
                 }
             } // JC adr
             0xdb => {
-                // self.a = self.read_port(self.read_memory(self.pc + 1));
+                self.a = bus.read_port(bus.read_memory(self.pc + 1));
                 self.pc += 2;
             } // IN D8
             0xdc => {
                 if self.get_flag_bit(FlagBit::Carry) {
-                    self.push(self.pc+3);
-                    self.pc = Self::join_bytes(self.read_memory(self.pc+2), self.read_memory(self.pc+1));
+                    self.push(bus, self.pc+3);
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc+2), bus.read_memory(self.pc+1));
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 3;
                 }
             } // CC adr
             0xde => {
-                self.sub_with_borrow(self.read_memory(self.pc+1));
+                self.sub_with_borrow(bus.read_memory(self.pc+1));
                 self.pc += 2;
             } // SBI D8
             0xdf => {
-                self.push(self.pc + 1);
+                self.push(bus, self.pc + 1);
                 self.pc = 3 * 0x8;
             }   // RST 3
             0xe0 => {
                 if !self.get_flag_bit(FlagBit::Parity) {
-                    self.pc = self.pop();
+                    self.pc = self.pop(bus);
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 1;
                 }
             }   // RPO
             0xe1 => {
-                let hl = self.pop();
+                let hl = self.pop(bus);
                 self.h = ((hl >> 8) & 0xFF) as u8;
                 self.l = (hl & 0xFF) as u8;
                 self.pc += 1;
             } // POP H
             0xe2 => {
                 if !self.get_flag_bit(FlagBit::Parity) {
-                    self.pc = Self::join_bytes(self.read_memory(self.pc + 2), self.read_memory(self.pc + 1));
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc + 2), bus.read_memory(self.pc + 1));
                 } else {
                     self.pc += 3;
                 }
             } // JPO adr
             0xe3 => {
-                std::mem::swap(&mut self.l, &mut self.memory[self.sp]);
-                std::mem::swap(&mut self.h, &mut self.memory[self.sp + 1]);
+                let (l, h) = (bus.read_memory(self.sp), bus.read_memory((self.sp + 1) & 0xFFFF));
+                bus.write_memory(self.sp, self.l);
+                bus.write_memory((self.sp + 1) & 0xFFFF, self.h);
+                self.l = l;
+                self.h = h;
                 self.pc += 1;
             } // XTHL
             0xe4 => {
                 if !self.get_flag_bit(FlagBit::Parity) {
-                    self.push(self.pc+3);
-                    self.pc = Self::join_bytes(self.read_memory(self.pc+2), self.read_memory(self.pc+1));
+                    self.push(bus, self.pc+3);
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc+2), bus.read_memory(self.pc+1));
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 3;
                 }
             } // CPO adr
             0xe5 => {
-                self.push(Self::join_bytes(self.h, self.l));
+                self.push(bus, Self::join_bytes(self.h, self.l));
                 self.pc += 1;
             } // PUSH H
             0xe6 => {
-                self.and_logical(self.read_memory(self.pc+1));
+                self.and_logical(bus.read_memory(self.pc+1));
                 self.pc += 2;
             } // ANI D8
             0xe7 => {
-                self.push(self.pc + 1);
+                self.push(bus, self.pc + 1);
                 self.pc = 4 * 0x8;
             }   // RST 4
             0xe8 => {
                 if self.get_flag_bit(FlagBit::Parity) {
-                    self.pc = self.pop();
+                    self.pc = self.pop(bus);
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 1;
                 }
@@ -1285,7 +1392,7 @@ impl I8080 {
             }   // PCHL
             0xea => {
                 if self.get_flag_bit(FlagBit::Parity) {
-                    self.pc = Self::join_bytes(self.read_memory(self.pc + 2), self.read_memory(self.pc + 1));
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc + 2), bus.read_memory(self.pc + 1));
                 } else {
                     self.pc += 3;
                 }
@@ -1297,36 +1404,38 @@ impl I8080 {
             } // XCHG
             0xec => {
                 if self.get_flag_bit(FlagBit::Parity) {
-                    self.push(self.pc+3);
-                    self.pc = Self::join_bytes(self.read_memory(self.pc+2), self.read_memory(self.pc+1));
+                    self.push(bus, self.pc+3);
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc+2), bus.read_memory(self.pc+1));
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 3;
                 }
             } // CPE adr
             0xee => {
-                self.xor_logical(self.read_memory(self.pc+1));
+                self.xor_logical(bus.read_memory(self.pc+1));
                 self.pc += 2;
             } // XRI D8
             0xef => {
-                self.push(self.pc+1);
+                self.push(bus, self.pc+1);
                 self.pc = 5 * 0x8;
             }   // RST 5
             0xf0 => {
                 if !self.get_flag_bit(FlagBit::Sign) {
-                    self.pc = self.pop();
+                    self.pc = self.pop(bus);
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 1;
                 }
             }   // RP
             0xf1 => {
-                let a_flags = self.pop();
+                let a_flags = self.pop(bus);
                 self.a = ((a_flags >> 8) & 0xFF) as u8;
-                self.flags = (a_flags & 0xFF) as u8;
+                self.flags = (a_flags & 0xFF) as u8 & 0b1101_0111 | 0b0000_0010;
                 self.pc += 1;
             } // POP PSW
             0xf2 => {
                 if !self.get_flag_bit(FlagBit::Sign) {
-                    self.pc = Self::join_bytes(self.read_memory(self.pc + 2), self.read_memory(self.pc + 1));
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc + 2), bus.read_memory(self.pc + 1));
                 } else {
                     self.pc += 3;
                 }
@@ -1337,27 +1446,29 @@ impl I8080 {
             }   // DI
             0xf4 => {
                 if !self.get_flag_bit(FlagBit::Sign) {
-                    self.push(self.pc+3);
-                    self.pc = Self::join_bytes(self.read_memory(self.pc+2), self.read_memory(self.pc+1));
+                    self.push(bus, self.pc+3);
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc+2), bus.read_memory(self.pc+1));
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 3;
                 }
             } // CP adr
             0xf5 => {
-                self.push(Self::join_bytes(self.a, self.flags));
+                self.push(bus, Self::join_bytes(self.a, self.flags));
                 self.pc += 1;
             } // PUSH PSW
             0xf6 => {
-                self.or_logical(self.read_memory(self.pc+1));
+                self.or_logical(bus.read_memory(self.pc+1));
                 self.pc += 2;
             } // ORI D8
             0xf7 => {
-                self.push(self.pc+1);
+                self.push(bus, self.pc+1);
                 self.pc = 6 * 0x8;
             }   // RST 6
             0xf8 => {
                 if self.get_flag_bit(FlagBit::Sign) {
-                    self.pc = self.pop();
+                    self.pc = self.pop(bus);
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 1;
                 }
@@ -1368,34 +1479,45 @@ impl I8080 {
             }   // SPHL
             0xfa => {
                 if self.get_flag_bit(FlagBit::Sign) {
-                    self.pc = Self::join_bytes(self.read_memory(self.pc + 2), self.read_memory(self.pc + 1));
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc + 2), bus.read_memory(self.pc + 1));
                 } else {
                     self.pc += 3;
                 }
             } // JM adr
             0xfb => {
                 self.enable_interrupts = true;
+                self.interrupt_delay = true;
                 self.pc += 1;
             } // EI
             0xfc => {
                 if self.get_flag_bit(FlagBit::Sign) {
-                    self.push(self.pc+3);
-                    self.pc = Self::join_bytes(self.read_memory(self.pc+2), self.read_memory(self.pc+1));
+                    self.push(bus, self.pc+3);
+                    self.pc = Self::join_bytes(bus.read_memory(self.pc+2), bus.read_memory(self.pc+1));
+                    cycles += CONDITION_MET_EXTRA_CYCLES;
                 } else {
                     self.pc += 3;
                 }
             } // CM adr
             0xfe => {
-                self.compare(self.read_memory(self.pc+1));
+                self.compare(bus.read_memory(self.pc+1));
                 self.pc += 2;
             } // CPI D8
             0xff => {
-                self.push(self.pc+1);
+                self.push(bus, self.pc+1);
                 self.pc = 7 * 0x8;
             } // RST 7 // CALL $38
-            _ => unreachable!(),
         }
 
-        return INSTR_CYCLES[instr as usize] as usize;
+        if let Some(int_num) = self.pending_interrupt.take() {
+            self.generate_interrupt(bus, int_num);
+        }
+
+        cycles
+    }
+}
+
+impl Default for I8080 {
+    fn default() -> Self {
+        Self::new()
     }
 }
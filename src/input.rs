@@ -0,0 +1,95 @@
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::event::Event;
+use sdl2::EventPump;
+use std::collections::BTreeMap;
+use space_invaders_emulator::{
+    controls::{Button, Controls, EmulatorAction},
+    machine::ResetKind,
+};
+
+
+/// Which key drives each cabinet button.
+pub struct KeyBindings {
+    keys: Vec<(Keycode, Button)>,
+}
+
+impl KeyBindings {
+    /// The default layout with `overrides` (button name to SDL key name) applied.
+    pub fn new(overrides: &BTreeMap<String, String>) -> Result<Self, String> {
+        let mut keys = vec![
+            (Keycode::Space, Button::Fire),
+            (Keycode::Num1, Button::P1Start),
+            (Keycode::Num2, Button::P2Start),
+            (Keycode::Left, Button::Left),
+            (Keycode::Right, Button::Right),
+            (Keycode::C, Button::Coin),
+            (Keycode::T, Button::Tilt),
+        ];
+
+        for (name, key_name) in overrides {
+            let button = Button::from_name(name).ok_or(format!("unknown button in key bindings: {}", name))?;
+            let key = Keycode::from_name(key_name).ok_or(format!("unknown key for {}: {}", name, key_name))?;
+            keys.retain(|&(_, bound)| bound != button);
+            keys.push((key, button));
+        }
+
+        Ok(KeyBindings { keys })
+    }
+
+    fn button(&self, key: Keycode) -> Option<Button> {
+        self.keys.iter().find(|&&(bound, _)| bound == key).map(|&(_, button)| button)
+    }
+}
+
+
+pub fn send_input(event_pump: &mut EventPump, controls: &mut Controls, bindings: &KeyBindings) -> EmulatorAction {
+    for event in event_pump.poll_iter() {
+        match event {
+            Event::Quit { .. } | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => return EmulatorAction::Quit,
+
+            Event::KeyDown { keycode: Some(Keycode::F1), .. } => return EmulatorAction::SaveState,
+            Event::KeyDown { keycode: Some(Keycode::F2), .. } => return EmulatorAction::LoadState,
+            Event::KeyDown { keycode: Some(Keycode::F3), .. } => return EmulatorAction::PreviousSlot,
+            Event::KeyDown { keycode: Some(Keycode::F4), .. } => return EmulatorAction::NextSlot,
+            Event::KeyDown { keycode: Some(Keycode::Backspace), repeat: false, .. } => return EmulatorAction::StartRewind,
+            Event::KeyUp { keycode: Some(Keycode::Backspace), .. } => return EmulatorAction::StopRewind,
+            Event::KeyDown { keycode: Some(Keycode::KpPlus), .. } => return EmulatorAction::IncreaseFPS,
+            Event::KeyDown { keycode: Some(Keycode::KpMinus), .. } => return EmulatorAction::DecreaseFPS,
+            Event::KeyDown { keycode: Some(Keycode::R), keymod, .. } => return if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                EmulatorAction::Reset(ResetKind::Soft)
+            } else {
+                EmulatorAction::Reset(ResetKind::Hard)
+            },
+            Event::KeyDown { keycode: Some(Keycode::M), .. } => return EmulatorAction::Mute,
+            Event::KeyDown { keycode: Some(Keycode::F5), .. } => return EmulatorAction::ToggleAudioCapture,
+            Event::KeyDown { keycode: Some(Keycode::F6), .. } => return EmulatorAction::BreakIntoDebugger,
+
+            Event::KeyDown { keycode: Some(Keycode::Num3), .. } => controls.lives = 3,
+            Event::KeyDown { keycode: Some(Keycode::Num4), .. } => controls.lives = 4,
+            Event::KeyDown { keycode: Some(Keycode::Num5), .. } => controls.lives = 5,
+            Event::KeyDown { keycode: Some(Keycode::Num6), .. } => controls.lives = 6,
+
+            Event::KeyDown { keycode: Some(Keycode::X), .. } => controls.extra_ship = !controls.extra_ship,
+
+            Event::KeyDown { keycode: Some(key), .. } => if let Some(button) = bindings.button(key) {
+                controls.set_button(button, true);
+            } else if let Some(slot) = key_to_slot(key) {
+                return EmulatorAction::SelectSlot(slot);
+            },
+            Event::KeyUp { keycode: Some(key), .. } => if let Some(button) = bindings.button(key) {
+                controls.set_button(button, false);
+            },
+            _ => {}
+        }
+    }
+
+    EmulatorAction::Nothing
+}
+
+fn key_to_slot(key: Keycode) -> Option<usize> {
+    let slot_keys = [
+        Keycode::Kp0, Keycode::Kp1, Keycode::Kp2, Keycode::Kp3, Keycode::Kp4,
+        Keycode::Kp5, Keycode::Kp6, Keycode::Kp7, Keycode::Kp8, Keycode::Kp9,
+    ];
+    slot_keys.iter().position(|&slot_key| slot_key == key)
+}
@@ -0,0 +1,22 @@
+//! Space Invaders arcade machine emulator core.
+//!
+//! Everything in this crate is frontend-agnostic: the CPU, the shift register,
+//! the input ports and the frame stepping live in [`machine::Machine`], while
+//! the SDL window and sound output are part of the binary only.
+
+pub mod i8080;
+pub mod shift_register;
+pub mod controls;
+pub mod screen;
+pub mod disassembler;
+pub mod machine;
+pub mod rom;
+pub mod cpm;
+pub mod save_state;
+pub mod rewind;
+pub mod movie;
+pub mod sound;
+pub mod synth;
+pub mod wav;
+pub mod mixer;
+pub mod debugger;
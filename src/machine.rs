@@ -0,0 +1,270 @@
+use crate::{
+    shift_register::ShiftRegister,
+    controls::Controls,
+    i8080::{I8080, Bus},
+    save_state::{StateError, StateReader},
+};
+// Only A0-A14 are decoded, the upper half of the address space mirrors the lower one
+const ADDRESS_MASK: usize = 0x7FFF;
+const ROM_END: usize = 0x2000;
+const RAM_END: usize = 0x4000;
+const RAM_MIRROR_START: usize = 0x6000;
+// What the CPU reads where nothing drives the bus: 0x4000-0x5FFF and the undecoded input ports
+const OPEN_BUS: u8 = 0xFF;
+
+/// A write to one of the sound ports, stamped with the emulated time it happened at.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PortWrite {
+    /// `SpaceInvadersBus::cycles` when the OUT instruction started
+    pub cycle: u64,
+    pub port: u8,
+    pub value: u8,
+}
+
+/// Memory and I/O ports of the Space Invaders board.
+/// Writes to the sound ports are queued so a frontend can play them after the frame.
+pub struct SpaceInvadersBus {
+    /// ROM at 0x0000-0x1FFF and RAM at 0x2000-0x3FFF, mirrors are folded onto these addresses
+    pub memory: [u8; 0x10000],
+    pub shift_register: ShiftRegister,
+    pub controls: Controls,
+    /// Last values written to the sound latches on ports 3 and 5
+    pub port3: u8,
+    pub port5: u8,
+    /// When set, writes to ROM or unmapped addresses are collected for `take_illegal_writes`
+    pub log_illegal_writes: bool,
+    /// CPU cycles run so far, idle time in HLT included. Not part of save states nor cleared
+    /// by reset, it keeps counting so frontends can pace audio by it.
+    pub cycles: u64,
+    sound_writes: Vec<PortWrite>,
+    illegal_writes: Vec<(usize, u8)>,
+}
+
+impl SpaceInvadersBus {
+    pub fn new() -> Self {
+        SpaceInvadersBus {
+            memory: [0; 0x10000],
+            shift_register: ShiftRegister::new(),
+            controls: Controls::new(),
+            port3: 0,
+            port5: 0,
+            log_illegal_writes: false,
+            cycles: 0,
+            sound_writes: Vec::new(),
+            illegal_writes: Vec::new(),
+        }
+    }
+
+    /// Maps a CPU address to its place in `memory`, None if nothing is there.
+    fn decode(address: usize) -> Option<usize> {
+        match address & ADDRESS_MASK {
+            address if address < RAM_END => Some(address),
+            address if address >= RAM_MIRROR_START => Some(address - (RAM_MIRROR_START - ROM_END)),
+            _ => None,
+        }
+    }
+
+    /// Port 5 bit 5, set by the game while the second player's turn is shown on a cocktail table.
+    pub fn flip_screen(&self) -> bool {
+        self.port5 & (0x1 << 5) != 0
+    }
+
+    /// Address and value of every illegal write since the last call.
+    pub fn take_illegal_writes(&mut self) -> Vec<(usize, u8)> {
+        std::mem::take(&mut self.illegal_writes)
+    }
+}
+
+impl Default for SpaceInvadersBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bus for SpaceInvadersBus {
+    fn read_memory(&self, address: usize) -> u8 {
+        match Self::decode(address) {
+            Some(address) => self.memory[address],
+            None => OPEN_BUS,
+        }
+    }
+
+    fn write_memory(&mut self, address: usize, value: u8) {
+        match Self::decode(address) {
+            Some(ram_address) if ram_address >= ROM_END => self.memory[ram_address] = value,
+            _ => if self.log_illegal_writes {
+                self.illegal_writes.push((address, value));
+            }
+        }
+    }
+
+    fn read_port(&mut self, port: u8) -> u8 {
+        match port {
+            0 => self.controls.read_controls(port), //
+            1 => self.controls.read_controls(port), // 1st player
+            2 => self.controls.read_controls(port), // 2nd player
+            3 => self.shift_register.read_value(),
+            _ => OPEN_BUS, // nothing answers on the other ports
+        }
+    }
+
+    fn write_port(&mut self, port: u8, acc: u8) {
+        match port {
+            2 => self.shift_register.set_shift_amount(acc),
+            3 => { // discrete sounds
+                self.port3 = acc;
+                self.sound_writes.push(PortWrite { cycle: self.cycles, port, value: acc });
+            }
+            4 => self.shift_register.put_value(acc),
+            5 => { // another sound
+                self.port5 = acc;
+                self.sound_writes.push(PortWrite { cycle: self.cycles, port, value: acc });
+            }
+            6 => { } // watch-dog timer?
+            _ => { } // not decoded by the board
+        }
+    }
+}
+
+
+/// Everything needed to put the machine back exactly where it was. ROM is not part of it.
+#[derive(Clone)]
+pub struct MachineState {
+    pub cpu: I8080,
+    pub ram: Vec<u8>,
+    pub shift_register: ShiftRegister,
+    pub controls: Controls,
+    pub port3: u8,
+    pub port5: u8,
+}
+
+impl MachineState {
+    pub(crate) fn write_to(&self, out: &mut Vec<u8>) {
+        self.cpu.write_state(out);
+        out.extend_from_slice(&self.ram);
+        self.shift_register.write_state(out);
+        self.controls.write_state(out);
+        out.extend_from_slice(&[self.port3, self.port5]);
+    }
+
+    pub(crate) fn read_from(reader: &mut StateReader) -> Result<Self, StateError> {
+        Ok(MachineState {
+            cpu: I8080::read_state(reader)?,
+            ram: reader.take(RAM_END - ROM_END)?.to_vec(),
+            shift_register: ShiftRegister::read_state(reader)?,
+            controls: Controls::read_state(reader)?,
+            port3: reader.u8()?,
+            port5: reader.u8()?,
+        })
+    }
+}
+
+
+/// Soft reset is the cabinet's reset line and keeps RAM, hard reset is a power cycle.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ResetKind {
+    Soft,
+    Hard,
+}
+
+
+/// The Space Invaders board without any frontend: the CPU and everything on its bus.
+pub struct Machine {
+    pub cpu: I8080,
+    pub bus: SpaceInvadersBus,
+    /// CRC32 of the loaded ROM set, save states are tied to it
+    pub rom_crc: u32,
+}
+
+impl Machine {
+    pub fn new() -> Self {
+        Machine {
+            cpu: I8080::new(),
+            bus: SpaceInvadersBus::new(),
+            rom_crc: 0,
+        }
+    }
+
+    pub fn load_rom(&mut self, rom: &[u8]) {
+        self.bus.memory[..rom.len()].copy_from_slice(rom);
+        self.rom_crc = crc32fast::hash(rom);
+    }
+
+    /// Runs one video frame: half a frame of cycles, the mid-screen interrupt (RST 1),
+    /// the other half and the vblank interrupt (RST 2).
+    pub fn run_frame(&mut self, clock_rate: u32, fps: f64) {
+        self.execute_n_cycles((0.5 * clock_rate as f64 / fps) as usize);
+        self.cpu.generate_interrupt(&mut self.bus, 1);
+
+        self.execute_n_cycles((0.5 * clock_rate as f64 / fps) as usize);
+        self.cpu.generate_interrupt(&mut self.bus, 2);
+    }
+
+    pub fn execute_n_cycles(&mut self, n: usize) -> Option<()> {
+        let mut current_rate = 0;
+        while current_rate < n {
+            if self.cpu.halted {
+                // nothing is fetched until the next interrupt, the rest of the slice is spent idle
+                self.bus.cycles += (n - current_rate) as u64;
+                break;
+            }
+
+            let instr = self.cpu.read_instr(&self.bus)?;
+            let len = self.cpu.execute(&mut self.bus, instr);
+            current_rate += len;
+            self.bus.cycles += len as u64;
+        }
+        Some(())
+    }
+
+    pub fn save_state(&self) -> MachineState {
+        MachineState {
+            cpu: self.cpu.clone(),
+            ram: self.bus.memory[ROM_END..RAM_END].to_vec(),
+            shift_register: self.bus.shift_register.clone(),
+            controls: self.bus.controls.clone(),
+            port3: self.bus.port3,
+            port5: self.bus.port5,
+        }
+    }
+
+    /// Restores a snapshot taken by `save_state`. Sound writes still queued are dropped,
+    /// frontends should resync their sound latches from `bus.port3` and `bus.port5`.
+    pub fn load_state(&mut self, state: &MachineState) {
+        self.cpu = state.cpu.clone();
+        self.bus.memory[ROM_END..RAM_END].copy_from_slice(&state.ram);
+        self.bus.shift_register = state.shift_register.clone();
+        self.bus.controls = state.controls.clone();
+        self.bus.port3 = state.port3;
+        self.bus.port5 = state.port5;
+        self.bus.sound_writes.clear();
+    }
+
+    /// Restarts the game from address 0 with the shift register and sound latches cleared.
+    /// The DIP switches and buttons in `bus.controls` are left alone, frontends should stop
+    /// their sounds since the latches are zero again.
+    pub fn reset(&mut self, kind: ResetKind) {
+        match kind {
+            ResetKind::Soft => self.cpu.reset(),
+            ResetKind::Hard => {
+                self.cpu = I8080::new();
+                self.bus.memory[ROM_END..RAM_END].fill(0);
+            }
+        }
+        self.bus.shift_register = ShiftRegister::new();
+        self.bus.port3 = 0;
+        self.bus.port5 = 0;
+        self.bus.sound_writes.clear();
+    }
+
+    /// Port 3 and port 5 writes since the last call, in the order the CPU made them.
+    pub fn take_sound_writes(&mut self) -> Vec<PortWrite> {
+        std::mem::take(&mut self.bus.sound_writes)
+    }
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
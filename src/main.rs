@@ -1,10 +1,18 @@
+mod args;
+mod config;
+mod console;
+mod error;
+mod headless;
+#[cfg(feature = "sdl")]
 mod sdl_context;
-mod shift_register;
-mod controls;
-mod screen;
+#[cfg(feature = "sdl")]
+mod input;
+#[cfg(feature = "sdl")]
 mod audio;
+#[cfg(feature = "sdl")]
+mod slot_picker;
+#[cfg(feature = "sdl")]
 mod bus;
-mod i8080;
 
 
 /*
@@ -18,17 +26,50 @@ Controls:
 
     Start lives         - Keys 3-6 (from 3 to 6 lives)
     Tilt                - Key T
-    Reset               - Key R
+    Reset               - Key R (power cycle), Shift+R (reset line only, keeps RAM)
 
     Save game state     - Key F1
     Load saved state    - Key F2
+    Select save slot    - Keys F3/F4 or Keypad 0-9
+    Rewind (hold)       - Backspace
     Mute/Unmute sound   - Key M
+    Record audio to WAV - Key F5 (toggle, files go to ./captures/)
+    Debugger            - Key F6 (pauses and reads commands from the terminal)
 */
 
 
-fn main() -> std::io::Result<()> {
-    let mut emul = bus::MainBus::new();
-    emul.run()?;
+fn main() {
+    let args = match args::Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", args::USAGE);
+        return;
+    }
 
-    Ok(())
-}
\ No newline at end of file
+    let result = if args.headless {
+        headless::run(&args)
+    } else {
+        run_with_window(&args)
+    };
+
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(feature = "sdl")]
+fn run_with_window(args: &args::Args) -> Result<(), error::EmulatorError> {
+    bus::MainBus::new(args)?.run()
+}
+
+#[cfg(not(feature = "sdl"))]
+fn run_with_window(_args: &args::Args) -> Result<(), error::EmulatorError> {
+    eprintln!("This build has no SDL frontend, use --headless or rebuild with the `sdl` feature.");
+    std::process::exit(1);
+}
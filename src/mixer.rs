@@ -0,0 +1,104 @@
+//! Software mixer that turns the sound port writes of a frame into PCM, paced by emulated time.
+//!
+//! Each write takes effect at the sample its cycle stamp falls on, so the audio of a frame lasts
+//! exactly as long as the frame did in emulated time whatever the clock rate or frame rate.
+
+use crate::{
+    machine::PortWrite,
+    sound::{Sound, SoundEvent, SoundLatches},
+};
+
+
+pub const SAMPLE_RATE: u32 = 44_100;
+
+pub struct SoundMixer {
+    sample_rate: u32,
+    /// Mono PCM of every sound at `sample_rate`, indexed by `Sound::index`
+    sounds: Vec<Vec<i16>>,
+    /// Playback position of every sound that is playing
+    voices: Vec<Option<usize>>,
+    latches: SoundLatches,
+    amplifier: bool,
+    /// Emulated time rendered so far
+    cycle: u64,
+    // cycles * sample_rate left over from the last span, so rounding never drifts
+    remainder: u64,
+}
+
+impl SoundMixer {
+    pub fn new(sounds: Vec<Vec<i16>>, sample_rate: u32) -> Self {
+        assert_eq!(sounds.len(), Sound::ALL.len());
+        SoundMixer {
+            sample_rate,
+            sounds,
+            voices: vec![None; Sound::ALL.len()],
+            latches: SoundLatches::new(),
+            amplifier: false,
+            cycle: 0,
+            remainder: 0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Renders emulated time up to cycle `until`, applying every write at its own cycle.
+    /// `writes` are the ones `Machine::take_sound_writes` returned for that stretch of time.
+    pub fn render(&mut self, writes: &[PortWrite], until: u64, clock_rate: u32) -> Vec<i16> {
+        let mut out = Vec::new();
+        for write in writes {
+            self.render_until(write.cycle, clock_rate, &mut out);
+            let events = self.latches.write(write.port, write.value);
+            self.apply(events);
+        }
+        self.render_until(until, clock_rate, &mut out);
+        out
+    }
+
+    /// Takes over the latches of a restored or reset machine, the sounds playing are cut off
+    /// and the UFO siren restarts if its bit is set. `cycle` is the machine's cycle count now.
+    pub fn restore(&mut self, port3: u8, port5: u8, cycle: u64) {
+        self.voices.iter_mut().for_each(|voice| *voice = None);
+        let events = self.latches.restore(port3, port5);
+        self.apply(events);
+        self.cycle = cycle;
+    }
+
+    fn apply(&mut self, events: Vec<SoundEvent>) {
+        for event in events {
+            match event {
+                SoundEvent::Start(sound) => self.voices[sound.index()] = Some(0),
+                SoundEvent::Stop(sound) => self.voices[sound.index()] = None,
+                SoundEvent::Amplifier(on) => self.amplifier = on,
+            }
+        }
+    }
+
+    fn render_until(&mut self, cycle: u64, clock_rate: u32, out: &mut Vec<i16>) {
+        let elapsed = cycle.saturating_sub(self.cycle) * self.sample_rate as u64 + self.remainder;
+        self.cycle = self.cycle.max(cycle);
+        self.remainder = elapsed % clock_rate as u64;
+
+        for _ in 0..elapsed / clock_rate as u64 {
+            let mut mixed = 0;
+            for (index, voice) in self.voices.iter_mut().enumerate() {
+                let samples = &self.sounds[index];
+                let position = match voice {
+                    Some(position) if *position < samples.len() => *position,
+                    Some(_) if Sound::ALL[index].looping() && !samples.is_empty() => 0,
+                    _ => {
+                        *voice = None;
+                        continue;
+                    }
+                };
+                mixed += samples[position] as i32;
+                *voice = Some(position + 1);
+            }
+
+            // voices keep running with the amplifier off, they are just not heard
+            let sample = if self.amplifier { mixed.clamp(i16::MIN as i32, i16::MAX as i32) as i16 } else { 0 };
+            out.push(sample);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Input movies: a starting machine state followed by the input port values of every frame.
+//! Replaying them on the deterministic core reproduces the recorded run exactly.
+//!
+//! Layout, all numbers little endian:
+//!     magic "SIEm", format version (u16), CRC32 of the ROM set (u32), clock rate (u32),
+//!     frames per second (f64 bits, u64), the starting `MachineState`,
+//!     frame count (u32), then ports 0, 1 and 2 for each frame.
+
+use crate::machine::{Machine, MachineState};
+use crate::save_state::{StateError, StateReader};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"SIEm";
+pub const FORMAT_VERSION: u16 = 1;
+
+pub struct Movie {
+    pub rom_crc: u32,
+    pub clock_rate: u32,
+    pub fps: f64,
+    pub start: MachineState,
+    pub frames: Vec<[u8; 3]>,
+}
+
+impl Movie {
+    /// Starts a recording from the machine's current state. Frames must then be run
+    /// with the same `clock_rate` and `fps` for the movie to replay faithfully.
+    pub fn record(machine: &Machine, clock_rate: u32, fps: f64) -> Self {
+        Movie {
+            rom_crc: machine.rom_crc,
+            clock_rate,
+            fps,
+            start: machine.save_state(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Call once per frame, before running it.
+    pub fn record_frame(&mut self, machine: &Machine) {
+        self.frames.push(machine.bus.controls.ports());
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.rom_crc.to_le_bytes());
+        bytes.extend_from_slice(&self.clock_rate.to_le_bytes());
+        bytes.extend_from_slice(&self.fps.to_bits().to_le_bytes());
+        self.start.write_to(&mut bytes);
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for ports in &self.frames {
+            bytes.extend_from_slice(ports);
+        }
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, bytes)
+    }
+
+    pub fn load(path: &Path, rom_crc: u32) -> Result<Self, StateError> {
+        let bytes = std::fs::read(path)?;
+        let mut reader = StateReader::new(&bytes);
+
+        if reader.take(MAGIC.len()).map_err(|_| StateError::NotAMovieFile)? != MAGIC {
+            return Err(StateError::NotAMovieFile);
+        }
+        let version = reader.u16()?;
+        if version != FORMAT_VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        let movie_rom_crc = reader.u32()?;
+        if movie_rom_crc != rom_crc {
+            return Err(StateError::RomMismatch { expected: rom_crc, found: movie_rom_crc });
+        }
+
+        let clock_rate = reader.u32()?;
+        let fps = f64::from_bits(reader.u64()?);
+        let start = MachineState::read_from(&mut reader)?;
+        let frame_count = reader.u32()? as usize;
+        let frames = (0..frame_count)
+            .map(|_| reader.take(3).map(|ports| [ports[0], ports[1], ports[2]]))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Movie { rom_crc, clock_rate, fps, start, frames })
+    }
+}
+
+
+/// Feeds a movie's inputs to a machine in place of the player's.
+pub struct MoviePlayer {
+    movie: Movie,
+    frame: usize,
+}
+
+impl MoviePlayer {
+    /// Puts the machine into the movie's starting state.
+    pub fn start(movie: Movie, machine: &mut Machine) -> Self {
+        machine.load_state(&movie.start);
+        MoviePlayer { movie, frame: 0 }
+    }
+
+    pub fn clock_rate(&self) -> u32 {
+        self.movie.clock_rate
+    }
+
+    pub fn fps(&self) -> f64 {
+        self.movie.fps
+    }
+
+    pub fn len(&self) -> usize {
+        self.movie.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.movie.frames.is_empty()
+    }
+
+    /// Sets the inputs for the next frame. Returns false once the movie is over.
+    pub fn next_frame(&mut self, machine: &mut Machine) -> bool {
+        match self.movie.frames.get(self.frame) {
+            Some(&ports) => {
+                machine.bus.controls.set_ports(ports);
+                self.frame += 1;
+                true
+            }
+            None => false,
+        }
+    }
+}
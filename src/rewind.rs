@@ -0,0 +1,65 @@
+use crate::machine::{Machine, MachineState};
+use std::collections::VecDeque;
+
+
+/// Ring buffer of recent machine snapshots for playing the game backwards.
+pub struct RewindBuffer {
+    states: VecDeque<MachineState>,
+    capacity: usize,
+    interval: usize,
+    frames_since_capture: usize,
+}
+
+impl RewindBuffer {
+    /// Keeps up to `capacity` snapshots, one taken every `interval` frames.
+    pub fn new(capacity: usize, interval: usize) -> Self {
+        RewindBuffer {
+            states: VecDeque::with_capacity(capacity),
+            capacity,
+            interval: interval.max(1),
+            frames_since_capture: 0,
+        }
+    }
+
+    /// Call once per frame before running it, the oldest snapshot is dropped when full.
+    pub fn capture(&mut self, machine: &Machine) {
+        self.frames_since_capture += 1;
+        if self.frames_since_capture < self.interval {
+            return;
+        }
+        self.frames_since_capture = 0;
+
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+        self.states.push_back(machine.save_state());
+    }
+
+    /// Steps the machine back to the newest snapshot and drops it. The player's current
+    /// inputs and DIP switches are kept. Returns false when there is nothing left to rewind.
+    pub fn rewind(&mut self, machine: &mut Machine) -> bool {
+        match self.states.pop_back() {
+            Some(state) => {
+                let controls = machine.bus.controls.clone();
+                machine.load_state(&state);
+                machine.bus.controls = controls;
+                self.frames_since_capture = 0;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+        self.frames_since_capture = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
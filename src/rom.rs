@@ -0,0 +1,155 @@
+//! Loading and verifying the Space Invaders ROM set, either as the four loose chip
+//! dumps or as a MAME style `invaders.zip`.
+
+use sha1::{Digest, Sha1};
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+
+
+pub const DEFAULT_ROM_DIR: &str = "./rom";
+pub const ZIP_NAME: &str = "invaders.zip";
+
+pub struct RomChip {
+    pub name: &'static str,
+    pub size: usize,
+    pub crc32: u32,
+    pub sha1: &'static str,
+}
+
+/// The chips of the MAME `invaders` set, in the order they sit in the address space.
+pub const INVADERS_CHIPS: [RomChip; 4] = [
+    RomChip { name: "invaders.h", size: 0x800, crc32: 0x734F_5AD8, sha1: "ff6200af4c9110d8181249cbcef1a8a40fa40b7f" },
+    RomChip { name: "invaders.g", size: 0x800, crc32: 0x6BFA_CA4A, sha1: "16f48649b531bdef8c2d1446c429b5f414524350" },
+    RomChip { name: "invaders.f", size: 0x800, crc32: 0x0CCE_AD96, sha1: "537aef03468f63c5b9e11dd61e253f7ae17d9743" },
+    RomChip { name: "invaders.e", size: 0x800, crc32: 0x14E5_38B0, sha1: "1d6ca0c99f9df71e2990b610deb9d7da0125e2d8" },
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChipProblem {
+    Missing,
+    WrongSize(usize),
+    WrongChecksum { crc32: u32 },
+}
+
+#[derive(Debug)]
+pub enum RomError {
+    Io(std::io::Error),
+    Zip(zip::result::ZipError),
+    /// Every chip that is missing or does not match the known dump
+    BadChips(Vec<(&'static str, ChipProblem)>),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomError::Io(err) => write!(f, "{}", err),
+            RomError::Zip(err) => write!(f, "{}", err),
+            RomError::BadChips(chips) => {
+                for (i, (name, problem)) in chips.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { ", " };
+                    match problem {
+                        ChipProblem::Missing => write!(f, "{}{} is missing", separator, name)?,
+                        ChipProblem::WrongSize(size) => write!(f, "{}{} has {} bytes", separator, name, size)?,
+                        ChipProblem::WrongChecksum { crc32 } => write!(f, "{}{} has CRC32 {:08x}", separator, name, crc32)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+impl From<std::io::Error> for RomError {
+    fn from(err: std::io::Error) -> Self {
+        RomError::Io(err)
+    }
+}
+
+impl From<zip::result::ZipError> for RomError {
+    fn from(err: zip::result::ZipError) -> Self {
+        RomError::Zip(err)
+    }
+}
+
+
+/// Loads the ROM set from `path`, which is either a zip file or a directory holding
+/// `invaders.zip` or the loose invaders.h, .g, .f and .e. Every chip is checked
+/// against the known dumps before anything is returned.
+pub fn load_si_rom(path: &Path) -> Result<Vec<u8>, RomError> {
+    let chips = if path.is_file() {
+        read_zip(path)?
+    } else if path.join(ZIP_NAME).is_file() {
+        read_zip(&path.join(ZIP_NAME))?
+    } else {
+        read_loose(path)?
+    };
+
+    verify(&chips)?;
+    Ok(chips.into_iter().flatten().flatten().collect())
+}
+
+fn read_loose(dir: &Path) -> Result<Vec<Option<Vec<u8>>>, RomError> {
+    INVADERS_CHIPS.iter().map(|chip| match std::fs::read(dir.join(chip.name)) {
+        Ok(data) => Ok(Some(data)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }).collect()
+}
+
+/// Entries are found by name, ignoring case and folders, or else by CRC like MAME does.
+fn read_zip(path: &Path) -> Result<Vec<Option<Vec<u8>>>, RomError> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+
+    let mut chips = Vec::new();
+    for chip in &INVADERS_CHIPS {
+        let mut found = None;
+        for index in 0..archive.len() {
+            let entry = archive.by_index(index)?;
+            let name = entry.name().rsplit('/').next().unwrap_or("");
+            if name.eq_ignore_ascii_case(chip.name) {
+                found = Some(index);
+                break;
+            }
+            if found.is_none() && entry.crc32() == chip.crc32 {
+                found = Some(index);
+            }
+        }
+
+        chips.push(match found {
+            Some(index) => {
+                let mut data = Vec::new();
+                archive.by_index(index)?.read_to_end(&mut data)?;
+                Some(data)
+            }
+            None => None,
+        });
+    }
+    Ok(chips)
+}
+
+fn verify(chips: &[Option<Vec<u8>>]) -> Result<(), RomError> {
+    let problems = INVADERS_CHIPS.iter().zip(chips).filter_map(|(chip, data)| {
+        let problem = match data {
+            None => ChipProblem::Missing,
+            Some(data) if data.len() != chip.size => ChipProblem::WrongSize(data.len()),
+            Some(data) if crc32fast::hash(data) != chip.crc32 || sha1_hex(data) != chip.sha1 => {
+                ChipProblem::WrongChecksum { crc32: crc32fast::hash(data) }
+            }
+            Some(_) => return None,
+        };
+        Some((chip.name, problem))
+    }).collect::<Vec<_>>();
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(RomError::BadChips(problems))
+    }
+}
+
+fn sha1_hex(data: &[u8]) -> String {
+    Sha1::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
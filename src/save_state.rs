@@ -0,0 +1,166 @@
+//! On-disk save states.
+//!
+//! Layout, all numbers little endian:
+//!     magic "SIEs", format version (u16), CRC32 of the ROM set (u32),
+//!     unix timestamp in seconds (u64), a grayscale thumbnail of
+//!     THUMBNAIL_WIDTH x THUMBNAIL_HEIGHT bytes, then the `MachineState` body.
+//!
+//! Version 1 had no thumbnail.
+
+use crate::machine::MachineState;
+use crate::screen::{THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAGIC: &[u8; 4] = b"SIEs";
+pub const FORMAT_VERSION: u16 = 2;
+pub const NUM_SLOTS: usize = 10;
+
+#[derive(Debug)]
+pub enum StateError {
+    Io(std::io::Error),
+    NotAStateFile,
+    NotAMovieFile,
+    UnsupportedVersion(u16),
+    RomMismatch { expected: u32, found: u32 },
+    Truncated,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::Io(err) => write!(f, "{}", err),
+            StateError::NotAStateFile => write!(f, "not a save state file"),
+            StateError::NotAMovieFile => write!(f, "not a movie file"),
+            StateError::UnsupportedVersion(version) => write!(f, "unsupported file format version {}", version),
+            StateError::RomMismatch { expected, found } => write!(f, "file was made for ROM set {:08X}, loaded ROM set is {:08X}", found, expected),
+            StateError::Truncated => write!(f, "file is truncated"),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+impl From<std::io::Error> for StateError {
+    fn from(err: std::io::Error) -> Self {
+        StateError::Io(err)
+    }
+}
+
+pub struct StateHeader {
+    pub version: u16,
+    pub rom_crc: u32,
+    pub timestamp: u64,
+    pub thumbnail: Vec<u8>,
+}
+
+pub fn slot_path(dir: &Path, slot: usize) -> PathBuf {
+    dir.join(format!("slot{}.sav", slot))
+}
+
+
+/// Cursor over a state file body that fails with `StateError::Truncated` instead of panicking.
+pub(crate) struct StateReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        StateReader { bytes }
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.bytes.len() < len {
+            return Err(StateError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, StateError> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, StateError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, StateError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, StateError> {
+        let mut value = [0; 8];
+        value.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(value))
+    }
+}
+
+
+/// `thumbnail` is what `ScreenDevice::thumbnail` produced for the saved frame,
+/// any other size is refused with `ErrorKind::InvalidInput`.
+pub fn save_to_file(path: &Path, state: &MachineState, rom_crc: u32, thumbnail: &[u8]) -> std::io::Result<()> {
+    if thumbnail.len() != THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+            format!("thumbnail is {} bytes, expected {}", thumbnail.len(), THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT)));
+    }
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&rom_crc.to_le_bytes());
+    bytes.extend_from_slice(&timestamp.to_le_bytes());
+    bytes.extend_from_slice(thumbnail);
+    state.write_to(&mut bytes);
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, bytes)
+}
+
+fn read_header(reader: &mut StateReader) -> Result<StateHeader, StateError> {
+    if reader.take(MAGIC.len()).map_err(|_| StateError::NotAStateFile)? != MAGIC {
+        return Err(StateError::NotAStateFile);
+    }
+    let version = reader.u16()?;
+    if version != FORMAT_VERSION {
+        return Err(StateError::UnsupportedVersion(version));
+    }
+
+    Ok(StateHeader {
+        version,
+        rom_crc: reader.u32()?,
+        timestamp: reader.u64()?,
+        thumbnail: reader.take(THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT)?.to_vec(),
+    })
+}
+
+/// Reads only the header, enough to show the slot in a picker.
+pub fn read_header_from_file(path: &Path) -> Result<StateHeader, StateError> {
+    let bytes = std::fs::read(path)?;
+    read_header(&mut StateReader::new(&bytes))
+}
+
+/// Reads a state file, rejecting files of another format version or made with a different ROM set.
+pub fn load_from_file(path: &Path, rom_crc: u32) -> Result<(StateHeader, MachineState), StateError> {
+    let bytes = std::fs::read(path)?;
+    let mut reader = StateReader::new(&bytes);
+
+    let header = read_header(&mut reader)?;
+    if header.rom_crc != rom_crc {
+        return Err(StateError::RomMismatch { expected: rom_crc, found: header.rom_crc });
+    }
+
+    let state = MachineState::read_from(&mut reader)?;
+    Ok((header, state))
+}
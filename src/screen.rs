@@ -1,52 +1,87 @@
-use sdl2::render::{Texture, WindowCanvas};
-use sdl2::rect::{Rect, Point};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
 
-pub struct ScreenDevice {}
+pub const SCREEN_WIDTH: usize = 224;
+pub const SCREEN_HEIGHT: usize = 256;
+pub const THUMBNAIL_WIDTH: usize = SCREEN_WIDTH / 2;
+pub const THUMBNAIL_HEIGHT: usize = SCREEN_HEIGHT / 2;
+
+#[derive(Default)]
+pub struct ScreenDevice {
+    /// Cocktail cabinets turn the picture around for the second player, see
+    /// `SpaceInvadersBus::flip_screen`. The colour overlay stays where it is on the glass.
+    pub flipped: bool,
+}
 
 impl ScreenDevice {
     pub fn new() -> Self {
-        ScreenDevice{}
+        ScreenDevice{ flipped: false }
     }
 
-    pub fn draw(&self, texture: &mut Texture, canvas: &mut WindowCanvas, memory: &[u8; 0x10000]) -> Result<(), String> {
+    /// Renders VRAM into an RGB24 buffer of SCREEN_WIDTH x SCREEN_HEIGHT pixels.
+    /// The picture comes out upside down, frontends flip it vertically when presenting.
+    pub fn draw(&self, buffer: &mut [u8], pitch: usize, memory: &[u8; 0x10000]) {
         // 256x224 (32 bytes width)
         let gfx_start: usize = 0x2400;
 
-        texture.with_lock(None, |buffer: &mut [u8], pitch: usize| {
-
-            for width in 0..256 {
-                for height in 0..224 {
-
-                    let width_byte = width / 8;
-                    let width_bit = width % 8;
+        for width in 0..256 {
+            for height in 0..224 {
 
-                    let byte = memory[gfx_start + 32 * height + width_byte];
+                let (src_width, src_height) = if self.flipped { (255 - width, 223 - height) } else { (width, height) };
+                let width_byte = src_width / 8;
+                let width_bit = src_width % 8;
 
-                    let offset = pitch * width + height * 3;
-                    if byte >> width_bit as u8 & 0x1 == 0x1 {
+                let byte = memory[gfx_start + 32 * src_height + width_byte];
 
-                        if (205..223).contains(&width) { // red flying sausage
-                            buffer[offset..=offset+2].copy_from_slice(&[255,0,0])
-                        } else if (16..72).contains(&width) || width < 16 && (20..112).contains(&height)  { // green player and shields
-                            buffer[offset..=offset+2].copy_from_slice(&[0,255,0])
-                        } else { // white everything else including ALIENS
-                            buffer[offset..=offset+2].copy_from_slice(&[255,255,255])
-                        }
+                let offset = pitch * width + height * 3;
+                if byte >> width_bit as u8 & 0x1 == 0x1 {
 
-                    } else { // black background
-                        buffer[offset..=offset+2].copy_from_slice(&[0,0,0])
+                    if (205..223).contains(&width) { // red flying sausage
+                        buffer[offset..=offset+2].copy_from_slice(&[255,0,0])
+                    } else if (16..72).contains(&width) || width < 16 && (20..112).contains(&height)  { // green player and shields
+                        buffer[offset..=offset+2].copy_from_slice(&[0,255,0])
+                    } else { // white everything else including ALIENS
+                        buffer[offset..=offset+2].copy_from_slice(&[255,255,255])
                     }
+
+                } else { // black background
+                    buffer[offset..=offset+2].copy_from_slice(&[0,0,0])
                 }
             }
-        })?;
+        }
+    }
 
-        let size = canvas.output_size().unwrap();
-        let dst_rect = Rect::new(0, 0, size.0, size.1);
-        let new_rect = Rect::new(0,0, size.1, size.0);
-        let cnt = Point::new((size.0 / 2) as i32, (size.1 / 2) as i32);
-        // let cnt = Point::new(200, 200);
+    /// Renders VRAM into an upright RGB24 image, rows top to bottom.
+    pub fn render(&self, memory: &[u8; 0x10000]) -> Vec<u8> {
+        let pitch = SCREEN_WIDTH * 3;
+        let mut buffer = vec![0_u8; pitch * SCREEN_HEIGHT];
+        self.draw(&mut buffer, pitch, memory);
 
-        canvas.copy_ex(&texture, None, None, 0.0, None, false, true)?;
-        Ok(())
+        buffer.chunks(pitch).rev().flatten().copied().collect()
     }
-}
\ No newline at end of file
+
+    /// Half-size upright grayscale picture of the screen, one byte per pixel.
+    /// A thumbnail pixel is lit if any of the four screen pixels it covers is.
+    pub fn thumbnail(&self, memory: &[u8; 0x10000]) -> Vec<u8> {
+        let frame = self.render(memory);
+        let lit = |x: usize, y: usize| frame[(y * SCREEN_WIDTH + x) * 3..][..3].iter().any(|&channel| channel != 0);
+
+        let mut thumbnail = Vec::with_capacity(THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT);
+        for y in 0..THUMBNAIL_HEIGHT {
+            for x in 0..THUMBNAIL_WIDTH {
+                let any_lit = lit(2 * x, 2 * y) || lit(2 * x + 1, 2 * y) || lit(2 * x, 2 * y + 1) || lit(2 * x + 1, 2 * y + 1);
+                thumbnail.push(if any_lit { 255 } else { 0 });
+            }
+        }
+        thumbnail
+    }
+}
+
+/// Writes an RGB24 image produced by `ScreenDevice::render` as a binary PPM file.
+pub fn write_ppm(path: &Path, rgb: &[u8]) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write!(file, "P6\n{} {}\n255\n", SCREEN_WIDTH, SCREEN_HEIGHT)?;
+    file.write_all(rgb)?;
+    file.flush()
+}
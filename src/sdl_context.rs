@@ -1,44 +1,56 @@
-use sdl2::pixels::{Color, PixelFormatEnum};
-use sdl2::render::Texture;
+use crate::error::EmulatorError;
+use sdl2::pixels::Color;
+use space_invaders_emulator::screen::{SCREEN_WIDTH, SCREEN_HEIGHT};
 use std::time::Duration;
 
 pub struct SdlContext {
-    pub sdl_context: sdl2::Sdl,
-    pub video_subsystem: sdl2::VideoSubsystem,
+    sdl_context: sdl2::Sdl, // keeps SDL initialised for the lifetime of the window
     pub canvas: sdl2::render::WindowCanvas,
     pub event_pump: sdl2::EventPump,
     pub texture_creator: sdl2::render::TextureCreator<sdl2::video::WindowContext>,
 }
 
 impl SdlContext {
-    pub fn new() -> Self {
-        let sdl_context = sdl2::init().unwrap();
-
-        // get monitor size
-        let monitor_size = sdl_context.video().unwrap().current_display_mode(0).unwrap();
-        let win_size = monitor_size.w.min(monitor_size.h) as u32 - 100;
-
-        let video_subsystem = sdl_context.video().unwrap();
-        let mut window = video_subsystem
-            .window("", win_size * 224/256, win_size)
+    /// `scale` sets the window to a multiple of the screen size, otherwise it fills most of the monitor.
+    pub fn new(scale: Option<u32>) -> Result<Self, EmulatorError> {
+        let sdl_context = sdl2::init().map_err(EmulatorError::Sdl)?;
+        let video_subsystem = sdl_context.video().map_err(EmulatorError::Sdl)?;
+
+        let (win_width, win_height) = match scale {
+            Some(scale) => (SCREEN_WIDTH as u32 * scale, SCREEN_HEIGHT as u32 * scale),
+            None => {
+                // get monitor size
+                let monitor_size = video_subsystem.current_display_mode(0).map_err(EmulatorError::Sdl)?;
+                let win_size = monitor_size.w.min(monitor_size.h) as u32 - 100;
+                (win_size * 224/256, win_size)
+            }
+        };
+
+        let window = video_subsystem
+            .window("", win_width, win_height)
             .position_centered()
             .resizable()
             .build()
-            .unwrap();
+            .map_err(|err| EmulatorError::Sdl(err.to_string()))?;
 
-        let mut canvas = window.into_canvas().accelerated().build().unwrap();
+        let mut canvas = window.into_canvas().accelerated().build()
+            .map_err(|err| EmulatorError::Sdl(err.to_string()))?;
         canvas.set_draw_color(Color::BLACK);
-        let mut event_pump = sdl_context.event_pump().unwrap();
+        let event_pump = sdl_context.event_pump().map_err(EmulatorError::Sdl)?;
         let texture_creator = canvas.texture_creator();
 
         canvas.set_draw_color(Color::BLUE);
 
-        SdlContext { sdl_context, video_subsystem, canvas, event_pump, texture_creator}
+        Ok(SdlContext { sdl_context, canvas, event_pump, texture_creator })
+    }
+
+    pub fn audio(&self) -> Result<sdl2::AudioSubsystem, String> {
+        self.sdl_context.audio()
     }
 
     pub fn sleep_for(&self, elapsed: Duration, fps: f64) {
         std::thread::sleep(Duration::from_secs_f64(1.0 / fps)
-            .checked_sub(elapsed).unwrap_or(Duration::from_millis(0)));
+            .checked_sub(elapsed).unwrap_or_else(|| Duration::from_millis(0)));
     }
 
-}
\ No newline at end of file
+}
@@ -1,4 +1,6 @@
-#[derive(Default)]
+use crate::save_state::{StateError, StateReader};
+
+#[derive(Clone, Default)]
 pub struct ShiftRegister {
     shift_amount: u8,
     shift_data: u16,
@@ -18,11 +20,23 @@ impl ShiftRegister {
 
     pub fn put_value(&mut self, val: u8) {
         self.shift_data >>= 8;
-        self.shift_data |= ((val as u16) << 8);
+        self.shift_data |= (val as u16) << 8;
     }
 
     pub fn read_value(&self) -> u8 {
         let bits_to_shift = (8 - self.shift_amount) as u16;
-        return ((self.shift_data >> bits_to_shift) & 0xFF) as u8;
+        ((self.shift_data >> bits_to_shift) & 0xFF) as u8
+    }
+
+    pub(crate) fn write_state(&self, out: &mut Vec<u8>) {
+        out.push(self.shift_amount);
+        out.extend_from_slice(&self.shift_data.to_le_bytes());
+    }
+
+    pub(crate) fn read_state(reader: &mut StateReader) -> Result<Self, StateError> {
+        Ok(ShiftRegister {
+            shift_amount: reader.u8()? & 0b111,
+            shift_data: reader.u16()?,
+        })
     }
 }
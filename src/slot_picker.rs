@@ -0,0 +1,112 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Texture, WindowCanvas};
+use space_invaders_emulator::{
+    screen::THUMBNAIL_WIDTH,
+    save_state::{self, NUM_SLOTS},
+};
+use std::path::{Path, PathBuf};
+
+// How long the picker stays on screen after the slot changes or is used
+const VISIBLE_FRAMES: u32 = 120;
+
+
+/// Save slot selection with an overlay showing which slots are used
+/// and the thumbnail of the selected one.
+pub struct SlotPicker {
+    dir: PathBuf,
+    pub slot: usize,
+    occupied: [bool; NUM_SLOTS],
+    thumbnail: Option<Vec<u8>>,
+    visible_frames: u32,
+}
+
+impl SlotPicker {
+    pub fn new(dir: &Path) -> Self {
+        let mut picker = SlotPicker {
+            dir: dir.to_path_buf(),
+            slot: 0,
+            occupied: [false; NUM_SLOTS],
+            thumbnail: None,
+            visible_frames: 0,
+        };
+        picker.refresh();
+        picker
+    }
+
+    pub fn slot_path(&self) -> PathBuf {
+        save_state::slot_path(&self.dir, self.slot)
+    }
+
+    pub fn select(&mut self, slot: usize) {
+        self.slot = slot % NUM_SLOTS;
+        self.refresh();
+        self.show();
+    }
+
+    pub fn next(&mut self) {
+        self.select(self.slot + 1);
+    }
+
+    pub fn previous(&mut self) {
+        self.select(self.slot + NUM_SLOTS - 1);
+    }
+
+    pub fn show(&mut self) {
+        self.visible_frames = VISIBLE_FRAMES;
+    }
+
+    /// Rereads the slot files, call after saving.
+    pub fn refresh(&mut self) {
+        for slot in 0..NUM_SLOTS {
+            self.occupied[slot] = save_state::slot_path(&self.dir, slot).exists();
+        }
+        self.thumbnail = save_state::read_header_from_file(&self.slot_path())
+            .ok()
+            .map(|header| header.thumbnail);
+    }
+
+    pub fn draw(&mut self, canvas: &mut WindowCanvas, thumbnail_texture: &mut Texture) -> Result<(), String> {
+        if self.visible_frames == 0 {
+            return Ok(());
+        }
+        self.visible_frames -= 1;
+
+        let (width, height) = canvas.output_size()?;
+        let previous_color = canvas.draw_color();
+
+        // selected slot thumbnail in the middle of the screen
+        let frame = Rect::new(width as i32 / 4, height as i32 / 8, width / 2, height / 2);
+        canvas.set_draw_color(Color::BLACK);
+        canvas.fill_rect(frame)?;
+        if let Some(thumbnail) = &self.thumbnail {
+            thumbnail_texture.with_lock(None, |buffer: &mut [u8], pitch: usize| {
+                for (y, row) in thumbnail.chunks(THUMBNAIL_WIDTH).enumerate() {
+                    for (x, &pixel) in row.iter().enumerate() {
+                        buffer[y * pitch + x * 3..][..3].copy_from_slice(&[pixel, pixel, pixel]);
+                    }
+                }
+            })?;
+            canvas.copy(thumbnail_texture, None, frame)?;
+        }
+        canvas.set_draw_color(Color::YELLOW);
+        canvas.draw_rect(frame)?;
+
+        // one box per slot, filled if it holds a state, the selected one highlighted
+        let box_size = width / (NUM_SLOTS as u32 * 2);
+        let row_x = (width - box_size * (NUM_SLOTS as u32 * 2 - 1)) as i32 / 2;
+        let row_y = (height * 3 / 4) as i32;
+        for slot in 0..NUM_SLOTS {
+            let slot_box = Rect::new(row_x + (slot as u32 * box_size * 2) as i32, row_y, box_size, box_size);
+            canvas.set_draw_color(if slot == self.slot { Color::YELLOW } else { Color::WHITE });
+            if self.occupied[slot] {
+                canvas.fill_rect(slot_box)?;
+            } else {
+                canvas.draw_rect(slot_box)?;
+            }
+        }
+
+        canvas.set_draw_color(previous_color);
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+//! The discrete sound effects of the cabinet and which sample file plays each of them.
+//!
+//! A sound directory may hold a `sounds.toml` manifest naming the file for each effect:
+//!
+//! ```toml
+//! ufo = "00.wav"
+//! shot = "01.wav"
+//! ```
+//!
+//! Effects it leaves out use the default names below.
+
+use crate::{synth, wav::Wav};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+
+pub const MANIFEST_NAME: &str = "sounds.toml";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Sound {
+    Ufo,
+    Shot,
+    PlayerDie,
+    InvaderDie,
+    Fleet1,
+    Fleet2,
+    Fleet3,
+    Fleet4,
+    UfoHit,
+}
+
+impl Sound {
+    pub const ALL: [Sound; 9] = [
+        Sound::Ufo, Sound::Shot, Sound::PlayerDie, Sound::InvaderDie,
+        Sound::Fleet1, Sound::Fleet2, Sound::Fleet3, Sound::Fleet4, Sound::UfoHit,
+    ];
+
+    /// Position in `Sound::ALL`, frontends use it as the channel number.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Name used for the sound in the manifest.
+    pub fn name(self) -> &'static str {
+        match self {
+            Sound::Ufo => "ufo",
+            Sound::Shot => "shot",
+            Sound::PlayerDie => "player_die",
+            Sound::InvaderDie => "invader_die",
+            Sound::Fleet1 => "fleet1",
+            Sound::Fleet2 => "fleet2",
+            Sound::Fleet3 => "fleet3",
+            Sound::Fleet4 => "fleet4",
+            Sound::UfoHit => "ufo_hit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Sound::ALL.iter().copied().find(|sound| sound.name() == name)
+    }
+
+    /// Output port and bit whose latch triggers the sound.
+    pub fn port_bit(self) -> (u8, u8) {
+        match self {
+            Sound::Ufo => (3, 0),
+            Sound::Shot => (3, 1),
+            Sound::PlayerDie => (3, 2),
+            Sound::InvaderDie => (3, 3),
+            Sound::Fleet1 => (5, 0),
+            Sound::Fleet2 => (5, 1),
+            Sound::Fleet3 => (5, 2),
+            Sound::Fleet4 => (5, 3),
+            Sound::UfoHit => (5, 4),
+        }
+    }
+
+    /// Looping sounds play for as long as their bit is set, the others play to the end once triggered.
+    pub fn looping(self) -> bool {
+        self == Sound::Ufo
+    }
+
+    /// Sample file used when the manifest does not name one.
+    pub fn default_file(self) -> &'static str {
+        match self {
+            Sound::Ufo => "00.wav",
+            Sound::Shot => "01.wav",
+            Sound::PlayerDie => "02.wav",
+            Sound::InvaderDie => "03.wav",
+            Sound::Fleet1 => "04.wav",
+            Sound::Fleet2 => "05.wav",
+            Sound::Fleet3 => "06.wav",
+            Sound::Fleet4 => "07.wav",
+            Sound::UfoHit => "08.wav",
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SoundEvent {
+    Start(Sound),
+    Stop(Sound),
+    /// Port 3 bit 5 switches the sound amplifier, the game keeps it off in attract mode
+    Amplifier(bool),
+}
+
+const AMPLIFIER_ENABLE_BIT: u8 = 0x1 << 5;
+
+/// The sound latches on ports 3 and 5, one per bit like on the sound board.
+/// A sound starts when its bit goes from 0 to 1, a looping sound stops when it goes back to 0.
+/// Sounds keep being triggered while the amplifier is off, they are just not heard.
+#[derive(Clone, Copy, Default)]
+pub struct SoundLatches {
+    port3: u8,
+    port5: u8,
+}
+
+impl SoundLatches {
+    pub fn new() -> Self {
+        SoundLatches::default()
+    }
+
+    /// Latches a write to port 3 or 5 and returns what it starts and stops,
+    /// any number of sounds can change at once.
+    pub fn write(&mut self, port: u8, value: u8) -> Vec<SoundEvent> {
+        let previous = match port {
+            3 => std::mem::replace(&mut self.port3, value),
+            5 => std::mem::replace(&mut self.port5, value),
+            _ => return Vec::new(),
+        };
+
+        let mut events = Sound::ALL.iter().copied().filter(|sound| sound.port_bit().0 == port).filter_map(|sound| {
+            let mask = 0x1 << sound.port_bit().1;
+            match (previous & mask != 0, value & mask != 0) {
+                (false, true) => Some(SoundEvent::Start(sound)),
+                (true, false) if sound.looping() => Some(SoundEvent::Stop(sound)),
+                _ => None,
+            }
+        }).collect::<Vec<_>>();
+
+        if port == 3 && (previous ^ value) & AMPLIFIER_ENABLE_BIT != 0 {
+            events.push(SoundEvent::Amplifier(self.amplifier_enabled()));
+        }
+        events
+    }
+
+    pub fn amplifier_enabled(&self) -> bool {
+        self.port3 & AMPLIFIER_ENABLE_BIT != 0
+    }
+
+    /// Takes over the latches of a restored machine: the amplifier state first, then only
+    /// looping sounds whose bit is set, one-shots that were playing at the time are not replayed.
+    pub fn restore(&mut self, port3: u8, port5: u8) -> Vec<SoundEvent> {
+        *self = SoundLatches::new();
+        let mut events = self.write(3, port3);
+        events.extend(self.write(5, port5));
+        events.retain(|event| matches!(event, SoundEvent::Start(sound) if sound.looping()));
+        events.insert(0, SoundEvent::Amplifier(self.amplifier_enabled()));
+        events
+    }
+}
+
+
+/// The sample file of every sound effect.
+pub struct SoundManifest {
+    files: Vec<PathBuf>,
+}
+
+impl SoundManifest {
+    /// Reads `sounds.toml` from `sound_dir` if there is one, file names are relative to `sound_dir`.
+    pub fn load(sound_dir: &Path) -> Result<Self, String> {
+        let mut files = Sound::ALL.iter().map(|sound| sound_dir.join(sound.default_file())).collect::<Vec<_>>();
+
+        let manifest_path = sound_dir.join(MANIFEST_NAME);
+        if !manifest_path.exists() {
+            return Ok(SoundManifest { files });
+        }
+
+        let text = std::fs::read_to_string(&manifest_path).map_err(|err| format!("{}: {}", manifest_path.display(), err))?;
+        let entries: BTreeMap<String, String> = toml::from_str(&text).map_err(|err| format!("{}: {}", manifest_path.display(), err))?;
+        for (name, file) in entries {
+            let sound = Sound::from_name(&name).ok_or(format!("{}: unknown sound {}", manifest_path.display(), name))?;
+            files[sound.index()] = sound_dir.join(file);
+        }
+
+        Ok(SoundManifest { files })
+    }
+
+    pub fn path(&self, sound: Sound) -> &Path {
+        &self.files[sound.index()]
+    }
+}
+
+
+/// PCM of every sound at `sample_rate`, indexed by `Sound::index`: the samples named by the
+/// manifest in `sound_dir`, synthesized for those that are missing or unreadable.
+/// The problems found are returned for the caller to report.
+pub fn load_sounds(sound_dir: &Path, sample_rate: u32) -> (Vec<Vec<i16>>, Vec<String>) {
+    let manifest = match SoundManifest::load(sound_dir) {
+        Ok(manifest) => manifest,
+        Err(err) => return (synthesize_sounds(sample_rate), vec![format!("{}, synthesizing all sounds", err)]),
+    };
+
+    let mut problems = Vec::new();
+    let sounds = Sound::ALL.iter().map(|&sound| {
+        let path = manifest.path(sound);
+        match Wav::read(path) {
+            Ok(wav) if !wav.samples.is_empty() => wav.resample(sample_rate),
+            result => {
+                let reason = result.err().map_or("no samples".to_string(), |err| err.to_string());
+                problems.push(format!("{} sound {}: {}, synthesizing it", sound.name(), path.display(), reason));
+                synth::synthesize(sound, sample_rate)
+            }
+        }
+    }).collect();
+
+    (sounds, problems)
+}
+
+/// Every sound from the synthesizer, indexed by `Sound::index`.
+pub fn synthesize_sounds(sample_rate: u32) -> Vec<Vec<i16>> {
+    Sound::ALL.iter().map(|&sound| synth::synthesize(sound, sample_rate)).collect()
+}
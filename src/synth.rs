@@ -0,0 +1,109 @@
+//! Procedural stand-ins for the cabinet's discrete sound circuits, for when no samples are available.
+//!
+//! Every sound is rendered once as mono 16 bit PCM. The shapes follow the analog board loosely:
+//! a warbling siren for the saucer, a falling sweep for the shot, filtered noise for the explosions
+//! and four short low tones for the fleet march.
+
+use crate::sound::Sound;
+use std::f64::consts::PI;
+
+
+const AMPLITUDE: f64 = 0.5 * i16::MAX as f64;
+// Fleet march notes, one per step of the four note loop
+const FLEET_FREQUENCIES: [f64; 4] = [73.4, 65.4, 61.7, 55.0];
+
+
+/// Renders `sound` at `sample_rate`. The saucer siren is a whole number of warble periods
+/// so it can be looped for as long as the saucer is on screen.
+pub fn synthesize(sound: Sound, sample_rate: u32) -> Vec<i16> {
+    let rate = sample_rate as f64;
+    let samples = match sound {
+        Sound::Ufo => siren(rate),
+        Sound::Shot => shot(rate),
+        Sound::PlayerDie => explosion(rate, 1.0, 0.08),
+        Sound::InvaderDie => explosion(rate, 0.3, 0.35),
+        Sound::Fleet1 => fleet(rate, FLEET_FREQUENCIES[0]),
+        Sound::Fleet2 => fleet(rate, FLEET_FREQUENCIES[1]),
+        Sound::Fleet3 => fleet(rate, FLEET_FREQUENCIES[2]),
+        Sound::Fleet4 => fleet(rate, FLEET_FREQUENCIES[3]),
+        Sound::UfoHit => ufo_hit(rate),
+    };
+
+    samples.into_iter().map(|sample| (sample.clamp(-1.0, 1.0) * AMPLITUDE) as i16).collect()
+}
+
+/// Sine whose pitch is swept by a triangle LFO, the phase is accumulated so the sweep has no clicks.
+fn sweep(rate: f64, duration: f64, frequency: impl Fn(f64) -> f64) -> Vec<f64> {
+    let mut phase = 0.0;
+    (0..(rate * duration) as usize).map(|i| {
+        phase += 2.0 * PI * frequency(i as f64 / rate) / rate;
+        phase.sin()
+    }).collect()
+}
+
+fn triangle(t: f64, period: f64) -> f64 {
+    let x = (t / period).fract();
+    if x < 0.5 { 4.0 * x - 1.0 } else { 3.0 - 4.0 * x }
+}
+
+fn siren(rate: f64) -> Vec<f64> {
+    let period = 0.16;
+    sweep(rate, period * 4.0, |t| 750.0 + 250.0 * triangle(t, period))
+}
+
+fn shot(rate: f64) -> Vec<f64> {
+    let duration = 0.25;
+    let tone = sweep(rate, duration, |t| 1200.0 - 3600.0 * t);
+    let mut noise = Noise::new();
+    tone.iter().enumerate().map(|(i, sample)| {
+        let envelope = 1.0 - i as f64 / tone.len() as f64;
+        (0.8 * sample + 0.2 * noise.next()) * envelope
+    }).collect()
+}
+
+/// Noise through a one pole low-pass, `brightness` sets the cutoff (0..1).
+fn explosion(rate: f64, duration: f64, brightness: f64) -> Vec<f64> {
+    let mut noise = Noise::new();
+    let mut filtered = 0.0;
+    let len = (rate * duration) as usize;
+    (0..len).map(|i| {
+        filtered += brightness * (noise.next() - filtered);
+        let envelope = (1.0 - i as f64 / len as f64).powi(2);
+        // the filter takes the level down, bring it back up
+        (filtered / brightness.sqrt()) * envelope
+    }).collect()
+}
+
+fn fleet(rate: f64, frequency: f64) -> Vec<f64> {
+    let len = (rate * 0.1) as usize;
+    (0..len).map(|i| {
+        let t = i as f64 / rate;
+        let square = if (t * frequency).fract() < 0.5 { 1.0 } else { -1.0 };
+        square * (-t * 30.0).exp()
+    }).collect()
+}
+
+fn ufo_hit(rate: f64) -> Vec<f64> {
+    let duration = 1.0;
+    let tone = sweep(rate, duration, |t| 400.0 + 800.0 * (t / 0.125).fract());
+    tone.iter().enumerate().map(|(i, sample)| sample * (1.0 - i as f64 / tone.len() as f64)).collect()
+}
+
+
+/// Xorshift white noise, seeded the same every time so rendering is reproducible.
+struct Noise {
+    state: u32,
+}
+
+impl Noise {
+    fn new() -> Self {
+        Noise { state: 0x1234_5678 }
+    }
+
+    fn next(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state as f64 / u32::MAX as f64 * 2.0 - 1.0
+    }
+}
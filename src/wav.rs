@@ -0,0 +1,127 @@
+//! Just enough of the WAV format for the sound samples and audio captures: uncompressed 8 or 16 bit PCM.
+
+use std::fs::File;
+use std::io::{BufWriter, Error, ErrorKind, Seek, SeekFrom, Write};
+use std::path::Path;
+
+
+/// Mono 16 bit PCM.
+pub struct Wav {
+    pub sample_rate: u32,
+    pub samples: Vec<i16>,
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+impl Wav {
+    /// Reads a PCM WAV file, stereo is mixed down to mono and 8 bit samples are widened.
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err(invalid("not a WAV file"));
+        }
+
+        let mut format = None;
+        let mut data = None;
+        let mut chunks = &bytes[12..];
+        while chunks.len() >= 8 {
+            let id = &chunks[0..4];
+            let len = u32::from_le_bytes([chunks[4], chunks[5], chunks[6], chunks[7]]) as usize;
+            let body = chunks.get(8..8 + len).ok_or_else(|| invalid("truncated chunk"))?;
+            match id {
+                b"fmt " if len >= 16 => format = Some(body),
+                b"data" => data = Some(body),
+                _ => {}
+            }
+            // chunks are padded to an even length
+            chunks = chunks.get(8 + len + len % 2..).unwrap_or(&[]);
+        }
+
+        let format = format.ok_or_else(|| invalid("no fmt chunk"))?;
+        let data = data.ok_or_else(|| invalid("no data chunk"))?;
+        let field = |offset: usize| u16::from_le_bytes([format[offset], format[offset + 1]]);
+        let (encoding, channels, bits) = (field(0), field(2) as usize, field(14));
+        let sample_rate = u32::from_le_bytes([format[4], format[5], format[6], format[7]]);
+        if encoding != 1 || channels == 0 || sample_rate == 0 {
+            return Err(invalid("only uncompressed PCM is supported"));
+        }
+
+        let frames: Vec<i32> = match bits {
+            8 => data.chunks_exact(channels)
+                .map(|frame| frame.iter().map(|&sample| (sample as i32 - 128) << 8).sum::<i32>() / channels as i32)
+                .collect(),
+            16 => data.chunks_exact(2 * channels)
+                .map(|frame| frame.chunks_exact(2).map(|sample| i16::from_le_bytes([sample[0], sample[1]]) as i32).sum::<i32>() / channels as i32)
+                .collect(),
+            _ => return Err(invalid("only 8 and 16 bit samples are supported")),
+        };
+
+        Ok(Wav { sample_rate, samples: frames.into_iter().map(|sample| sample as i16).collect() })
+    }
+
+    /// The samples at another rate, by linear interpolation.
+    pub fn resample(&self, sample_rate: u32) -> Vec<i16> {
+        if sample_rate == self.sample_rate || self.samples.is_empty() {
+            return self.samples.clone();
+        }
+
+        let step = self.sample_rate as f64 / sample_rate as f64;
+        let len = (self.samples.len() as f64 / step) as usize;
+        (0..len).map(|i| {
+            let position = i as f64 * step;
+            let index = position as usize;
+            let next = self.samples.get(index + 1).copied().unwrap_or(self.samples[index]);
+            let fraction = position - index as f64;
+            (self.samples[index] as f64 * (1.0 - fraction) + next as f64 * fraction) as i16
+        }).collect()
+    }
+}
+
+
+/// Streams mono 16 bit PCM to a WAV file. The header is brought up to date after every write,
+/// so the file is valid at any point even if the program never gets to close it.
+pub struct WavWriter {
+    file: BufWriter<File>,
+    data_len: u32,
+}
+
+impl WavWriter {
+    pub fn create(path: &Path, sample_rate: u32) -> std::io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = BufWriter::new(File::create(path)?);
+
+        file.write_all(b"RIFF")?;
+        file.write_all(&36_u32.to_le_bytes())?;
+        file.write_all(b"WAVEfmt ")?;
+        file.write_all(&16_u32.to_le_bytes())?;
+        file.write_all(&1_u16.to_le_bytes())?; // PCM
+        file.write_all(&1_u16.to_le_bytes())?; // mono
+        file.write_all(&sample_rate.to_le_bytes())?;
+        file.write_all(&(sample_rate * 2).to_le_bytes())?; // bytes per second
+        file.write_all(&2_u16.to_le_bytes())?; // bytes per frame
+        file.write_all(&16_u16.to_le_bytes())?; // bits per sample
+        file.write_all(b"data")?;
+        file.write_all(&0_u32.to_le_bytes())?;
+        file.flush()?;
+
+        Ok(WavWriter { file, data_len: 0 })
+    }
+
+    pub fn write(&mut self, samples: &[i16]) -> std::io::Result<()> {
+        for sample in samples {
+            self.file.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_len += 2 * samples.len() as u32;
+
+        self.file.seek(SeekFrom::Start(4))?;
+        self.file.write_all(&(36 + self.data_len).to_le_bytes())?;
+        self.file.seek(SeekFrom::Start(40))?;
+        self.file.write_all(&self.data_len.to_le_bytes())?;
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.flush()
+    }
+}
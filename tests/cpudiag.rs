@@ -0,0 +1,10 @@
+use space_invaders_emulator::cpm::CpmMachine;
+
+#[test]
+fn cpudiag_reports_operational_cpu() {
+    let program = std::fs::read("rom/tests/cpudiag.bin").unwrap();
+    let mut machine = CpmMachine::new(&program);
+
+    assert!(machine.run(10_000_000), "cpudiag did not exit, console: {}", machine.console);
+    assert!(machine.console.contains("CPU IS OPERATIONAL"), "console: {}", machine.console);
+}
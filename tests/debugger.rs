@@ -0,0 +1,86 @@
+use space_invaders_emulator::debugger::Debugger;
+use space_invaders_emulator::machine::Machine;
+use space_invaders_emulator::rom::{load_si_rom, DEFAULT_ROM_DIR};
+use std::path::Path;
+
+const CLOCK_RATE: u32 = 2_000_000;
+const FPS: f64 = 60.0;
+
+fn booted_machine() -> Machine {
+    let mut machine = Machine::new();
+    machine.load_rom(&load_si_rom(Path::new(DEFAULT_ROM_DIR)).unwrap());
+    machine
+}
+
+// LXI SP,2400; CALL 0010; JMP 0006; ... 0010: NOP; RET
+fn call_program() -> Machine {
+    let mut program = vec![0; 0x20];
+    program[..9].copy_from_slice(&[0x31, 0x00, 0x24, 0xcd, 0x10, 0x00, 0xc3, 0x06, 0x00]);
+    program[0x10..0x12].copy_from_slice(&[0x00, 0xc9]);
+    let mut machine = Machine::new();
+    machine.load_rom(&program);
+    machine
+}
+
+#[test]
+fn frames_run_under_the_debugger_match_plain_frames() {
+    let mut plain = booted_machine();
+    let mut debugged = booted_machine();
+    let mut debugger = Debugger::new();
+    // a breakpoint in the game's main loop keeps the debugger on the instruction by instruction path
+    debugger.add_breakpoint(0x0ace);
+
+    for _ in 0..300 {
+        plain.run_frame(CLOCK_RATE, FPS);
+        while !debugger.run(&mut debugged, CLOCK_RATE, FPS) {
+            // single steps in between must not shift the interrupts either
+            if debugger.step(&mut debugged, CLOCK_RATE, FPS) {
+                break;
+            }
+            debugger.resume();
+        }
+    }
+
+    assert!(plain.bus.memory[..] == debugged.bus.memory[..]);
+    assert_eq!(plain.cpu.pc, debugged.cpu.pc);
+    assert_eq!(plain.bus.cycles, debugged.bus.cycles);
+}
+
+#[test]
+fn breakpoint_pauses_before_the_instruction_and_resuming_passes_it() {
+    let mut machine = call_program();
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x0010);
+
+    assert!(!debugger.run(&mut machine, CLOCK_RATE, FPS));
+    assert!(debugger.paused());
+    assert_eq!(machine.cpu.pc, 0x0010);
+
+    debugger.resume();
+    debugger.step(&mut machine, CLOCK_RATE, FPS);
+    assert_eq!(machine.cpu.pc, 0x0011);
+
+    assert!(debugger.remove_breakpoint(0x0010));
+    assert!(!debugger.remove_breakpoint(0x0010));
+    assert!(debugger.run(&mut machine, CLOCK_RATE, FPS));
+}
+
+#[test]
+fn step_over_runs_the_call_until_it_returns() {
+    let mut machine = call_program();
+    let mut debugger = Debugger::new();
+    debugger.pause();
+    debugger.step(&mut machine, CLOCK_RATE, FPS);
+    assert_eq!(machine.cpu.pc, 0x0003);
+
+    assert!(!debugger.step_over(&mut machine, CLOCK_RATE, FPS));
+    assert!(!debugger.run(&mut machine, CLOCK_RATE, FPS));
+    assert!(debugger.paused());
+    assert_eq!(machine.cpu.pc, 0x0006);
+    assert_eq!(machine.cpu.sp, 0x2400);
+
+    // anything but a call is a single step
+    debugger.step_over(&mut machine, CLOCK_RATE, FPS);
+    assert!(debugger.paused());
+    assert_eq!(machine.cpu.pc, 0x0006);
+}
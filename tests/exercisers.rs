@@ -0,0 +1,60 @@
+//! The classic CP/M 8080 exercisers are not shipped with the repo. Put 8080PRE.COM,
+//! TST8080.COM, CPUTEST.COM and 8080EXM.COM into `rom/tests/` (or the directory named
+//! by `I8080_EXERCISERS`) to run them, missing files are skipped.
+//!
+//! 8080EXM takes billions of cycles, run it with `cargo test --release -- --ignored`.
+
+use space_invaders_emulator::cpm::{exerciser_results, CpmMachine};
+use std::path::PathBuf;
+
+fn run_exerciser(file_name: &str, max_cycles: u64) -> Option<CpmMachine> {
+    let dir = std::env::var("I8080_EXERCISERS").unwrap_or_else(|_| "rom/tests".to_string());
+    let path = PathBuf::from(dir).join(file_name);
+    let program = match std::fs::read(&path) {
+        Ok(program) => program,
+        Err(_) => {
+            eprintln!("skipping {}: {} not found", file_name, path.display());
+            return None;
+        }
+    };
+
+    let mut machine = CpmMachine::new(&program);
+    assert!(machine.run(max_cycles), "{} did not exit, console: {}", file_name, machine.console);
+    Some(machine)
+}
+
+#[test]
+fn preliminary_8080pre() {
+    if let Some(machine) = run_exerciser("8080PRE.COM", 10_000_000) {
+        assert!(machine.console.contains("8080 Preliminary tests complete"), "console: {}", machine.console);
+    }
+}
+
+#[test]
+fn tst8080() {
+    if let Some(machine) = run_exerciser("TST8080.COM", 10_000_000) {
+        assert!(machine.console.contains("CPU IS OPERATIONAL"), "console: {}", machine.console);
+    }
+}
+
+#[test]
+fn cputest() {
+    if let Some(machine) = run_exerciser("CPUTEST.COM", 1_000_000_000) {
+        assert!(machine.console.contains("CPU TESTS OK"), "console: {}", machine.console);
+    }
+}
+
+#[test]
+#[ignore]
+fn exerciser_8080exm() {
+    if let Some(machine) = run_exerciser("8080EXM.COM", 100_000_000_000) {
+        let results = exerciser_results(&machine.console);
+        for result in &results {
+            println!("{:<30} {:<6} {:08x?}", result.name, if result.passed { "ok" } else { "FAILED" }, result.crc);
+        }
+
+        assert!(!results.is_empty(), "console: {}", machine.console);
+        let failed: Vec<_> = results.iter().filter(|result| !result.passed).map(|result| &result.report).collect();
+        assert!(failed.is_empty(), "failed tests: {:#?}", failed);
+    }
+}
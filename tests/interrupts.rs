@@ -0,0 +1,43 @@
+use space_invaders_emulator::i8080::{I8080, Ram};
+
+fn step(cpu: &mut I8080, ram: &mut Ram) -> usize {
+    let instr = cpu.read_instr(ram).unwrap();
+    cpu.execute(ram, instr)
+}
+
+#[test]
+fn ei_hlt_waits_for_interrupt_and_resumes_after_hlt() {
+    let mut cpu = I8080::new();
+    let mut ram = Ram::new();
+    ram.load(&[0xfb, 0x76, 0x00], 0x100); // EI; HLT; NOP
+    cpu.pc = 0x100;
+    cpu.sp = 0x2400;
+
+    step(&mut cpu, &mut ram);
+    cpu.generate_interrupt(&mut ram, 1); // held back until the instruction after EI completes
+    assert_eq!(cpu.pc, 0x101);
+
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.pc, 0x08);
+    assert!(!cpu.halted);
+    assert_eq!(cpu.pop(&ram), 0x102);
+}
+
+#[test]
+fn halted_cpu_stays_halted_until_interrupt() {
+    let mut cpu = I8080::new();
+    let mut ram = Ram::new();
+    ram.load(&[0xfb, 0x00, 0x76], 0x100); // EI; NOP; HLT
+    cpu.pc = 0x100;
+    cpu.sp = 0x2400;
+
+    for _ in 0..3 {
+        step(&mut cpu, &mut ram);
+    }
+    assert!(cpu.halted);
+
+    cpu.generate_interrupt(&mut ram, 2);
+    assert!(!cpu.halted);
+    assert_eq!(cpu.pc, 0x10);
+    assert_eq!(cpu.pop(&ram), 0x103);
+}
@@ -0,0 +1,83 @@
+use space_invaders_emulator::i8080::Bus;
+use space_invaders_emulator::machine::Machine;
+
+fn machine_with_rom() -> Machine {
+    let mut machine = Machine::new();
+    let rom: Vec<u8> = (0..0x2000).map(|address| (address % 251) as u8).collect();
+    machine.load_rom(&rom);
+    machine
+}
+
+#[test]
+fn rom_is_write_protected() {
+    let mut machine = machine_with_rom();
+    for &address in &[0x0000, 0x1234, 0x1FFF] {
+        let before = machine.bus.read_memory(address);
+        machine.bus.write_memory(address, !before);
+        assert_eq!(machine.bus.read_memory(address), before);
+    }
+}
+
+#[test]
+fn ram_is_mirrored_at_0x6000() {
+    let mut machine = machine_with_rom();
+    machine.bus.write_memory(0x6000, 0xAA);
+    assert_eq!(machine.bus.read_memory(0x2000), 0xAA);
+    machine.bus.write_memory(0x3FFF, 0x55);
+    assert_eq!(machine.bus.read_memory(0x7FFF), 0x55);
+    assert_eq!(machine.bus.memory[0x2000], 0xAA);
+}
+
+#[test]
+fn unmapped_addresses_read_open_bus_and_ignore_writes() {
+    let mut machine = machine_with_rom();
+    for &address in &[0x4000, 0x4ABC, 0x5FFF] {
+        machine.bus.write_memory(address, 0x12);
+        assert_eq!(machine.bus.read_memory(address), 0xFF);
+    }
+    assert!(machine.bus.memory[0x4000..0x6000].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn addresses_wrap_at_0x8000() {
+    let mut machine = machine_with_rom();
+    assert_eq!(machine.bus.read_memory(0x8123), machine.bus.read_memory(0x0123));
+    machine.bus.write_memory(0xA345, 0x77);
+    assert_eq!(machine.bus.read_memory(0x2345), 0x77);
+    assert_eq!(machine.bus.read_memory(0xE345), 0x77);
+    assert_eq!(machine.bus.read_memory(0xC000), 0xFF);
+}
+
+#[test]
+fn illegal_writes_are_logged_when_asked_to() {
+    let mut machine = machine_with_rom();
+    machine.bus.write_memory(0x0100, 0x01);
+    assert!(machine.bus.take_illegal_writes().is_empty());
+
+    machine.bus.log_illegal_writes = true;
+    machine.bus.write_memory(0x0100, 0x01);
+    machine.bus.write_memory(0x2100, 0x02);
+    machine.bus.write_memory(0x4100, 0x03);
+    machine.bus.write_memory(0x8100, 0x04);
+    assert_eq!(machine.bus.take_illegal_writes(), vec![(0x0100, 0x01), (0x4100, 0x03), (0x8100, 0x04)]);
+    assert!(machine.bus.take_illegal_writes().is_empty());
+}
+
+#[test]
+fn inr_and_dcr_m_set_flags_from_the_result_where_writes_are_lost() {
+    // ROM holding 0xFF and 0x01, and open bus reading 0xFF, all turn into zero
+    for &(address, instr) in &[(0x0020, 0x34), (0x4000, 0x34), (0x0021, 0x35)] {
+        // LXI SP,2400; LXI H,address; INR M (or DCR M); PUSH PSW; HLT
+        let mut rom = vec![0x31, 0x00, 0x24, 0x21, address as u8, (address >> 8) as u8, instr, 0xf5, 0x76];
+        rom.resize(0x20, 0);
+        rom.extend_from_slice(&[0xFF, 0x01]);
+        let mut machine = Machine::new();
+        machine.load_rom(&rom);
+        machine.execute_n_cycles(10 + 10 + 10 + 11 + 7);
+
+        assert!(machine.cpu.halted);
+        assert_eq!(machine.bus.read_memory(address), rom.get(address).copied().unwrap_or(0xFF));
+        // S clear, Z set, AC set, P set (even parity of zero), carry untouched
+        assert_eq!(machine.bus.read_memory(0x23FE), 0b0101_0110, "{:02X} at {:04X}", instr, address);
+    }
+}
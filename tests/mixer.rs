@@ -0,0 +1,56 @@
+use space_invaders_emulator::machine::PortWrite;
+use space_invaders_emulator::mixer::SoundMixer;
+use space_invaders_emulator::sound::Sound;
+
+const RATE: u32 = 8_000;
+
+// every sound a constant level of its own so the mix shows which ones are playing
+fn mixer() -> SoundMixer {
+    let sounds = Sound::ALL.iter().map(|&sound| vec![1 << sound.index(); 100]).collect();
+    SoundMixer::new(sounds, RATE)
+}
+
+fn write(cycle: u64, port: u8, value: u8) -> PortWrite {
+    PortWrite { cycle, port, value }
+}
+
+#[test]
+fn audio_length_follows_emulated_time_at_any_clock_rate() {
+    for &clock_rate in &[2_000_000, 2_300_000, 1_000_003] {
+        let mut mixer = mixer();
+        let frame_cycles = clock_rate as u64 / 60;
+        let total = (1..=60).map(|frame| mixer.render(&[], frame * frame_cycles, clock_rate).len()).sum::<usize>();
+        assert_eq!(total, (60 * frame_cycles * RATE as u64 / clock_rate as u64) as usize);
+    }
+}
+
+#[test]
+fn writes_take_effect_at_their_cycle() {
+    let mut mixer = mixer();
+    // amplifier on at the start, shot half way through a 1/100 s span at 1 MHz
+    let samples = mixer.render(&[write(0, 3, 0b10_0000), write(5_000, 3, 0b10_0010)], 10_000, 1_000_000);
+    assert_eq!(samples.len(), 80);
+    assert!(samples[..40].iter().all(|&sample| sample == 0));
+    assert!(samples[40..].iter().all(|&sample| sample == 1 << Sound::Shot.index()));
+}
+
+#[test]
+fn nothing_is_heard_with_the_amplifier_off() {
+    let mut mixer = mixer();
+    let samples = mixer.render(&[write(0, 5, 0b1)], 10_000, 1_000_000);
+    assert!(samples.iter().all(|&sample| sample == 0));
+}
+
+#[test]
+fn ufo_loops_until_its_bit_clears_and_one_shots_end() {
+    let mut mixer = mixer();
+    let ufo = 1 << Sound::Ufo.index();
+    let fleet = 1 << Sound::Fleet1.index();
+    // 1000 samples, ten times the length of every sound
+    let samples = mixer.render(&[write(0, 3, 0b10_0001), write(0, 5, 0b1)], 125_000, 1_000_000);
+    assert_eq!(samples[50], ufo + fleet);
+    assert_eq!(samples[999], ufo);
+
+    let samples = mixer.render(&[write(125_000, 3, 0b10_0000)], 250_000, 1_000_000);
+    assert!(samples.iter().all(|&sample| sample == 0));
+}
@@ -0,0 +1,58 @@
+use space_invaders_emulator::controls::{Button, Controls};
+use space_invaders_emulator::machine::Machine;
+use space_invaders_emulator::rom::{load_si_rom, DEFAULT_ROM_DIR};
+use space_invaders_emulator::movie::{Movie, MoviePlayer};
+use std::path::Path;
+
+#[test]
+fn controls_round_trip_through_port_values() {
+    let mut controls = Controls::new();
+    controls.set_button(Button::Coin, true);
+    controls.set_button(Button::Left, true);
+    controls.lives = 5;
+    controls.extra_ship = true;
+
+    let mut decoded = Controls::new();
+    decoded.set_ports(controls.ports());
+    assert_eq!(decoded.ports(), controls.ports());
+    assert_eq!(decoded.lives, 5);
+    assert!(decoded.extra_ship);
+}
+
+#[test]
+fn played_back_movie_reproduces_the_recorded_run() {
+    let rom = load_si_rom(Path::new(DEFAULT_ROM_DIR)).unwrap();
+    let mut machine = Machine::new();
+    machine.load_rom(&rom);
+    machine.run_frame(2_000_000, 60.0);
+
+    let mut movie = Movie::record(&machine, 2_000_000, 60.0);
+    for frame in 0..300 {
+        // insert a coin, start a game and shoot while moving left
+        machine.bus.controls.set_button(Button::Coin, frame == 10);
+        machine.bus.controls.set_button(Button::P1Start, frame == 60);
+        machine.bus.controls.set_button(Button::Fire, frame % 20 < 10);
+        machine.bus.controls.set_button(Button::Left, frame > 150);
+        movie.record_frame(&machine);
+        machine.run_frame(2_000_000, 60.0);
+    }
+    let recorded = machine.save_state();
+
+    let path = std::env::temp_dir().join(format!("space_invaders_movie_{}.mov", std::process::id()));
+    movie.save(&path).unwrap();
+    let movie = Movie::load(&path, machine.rom_crc).unwrap();
+    assert!(Movie::load(&path, 0).is_err());
+    std::fs::remove_file(&path).unwrap();
+
+    let mut replay = Machine::new();
+    replay.load_rom(&rom);
+    let mut player = MoviePlayer::start(movie, &mut replay);
+    assert_eq!(player.len(), 300);
+    while player.next_frame(&mut replay) {
+        replay.run_frame(player.clock_rate(), player.fps());
+    }
+
+    let replayed = replay.save_state();
+    assert!(replayed.ram == recorded.ram);
+    assert_eq!(replayed.cpu.pc, recorded.cpu.pc);
+}
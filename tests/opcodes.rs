@@ -0,0 +1,117 @@
+use space_invaders_emulator::i8080::{I8080, Ram};
+
+const START: usize = 0x100;
+const STACK: usize = 0x2400;
+
+// the instruction at START, with the stack pointer at STACK
+fn setup(code: &[u8]) -> (I8080, Ram) {
+    let mut cpu = I8080::new();
+    let mut ram = Ram::new();
+    ram.load(code, START);
+    cpu.pc = START;
+    cpu.sp = STACK;
+    (cpu, ram)
+}
+
+fn step(cpu: &mut I8080, ram: &mut Ram) -> usize {
+    let instr = cpu.read_instr(ram).unwrap();
+    cpu.execute(ram, instr)
+}
+
+#[test]
+fn undocumented_nops() {
+    for &instr in &[0x08, 0x10, 0x18, 0x20, 0x28, 0x30, 0x38] {
+        let (mut cpu, mut ram) = setup(&[instr]);
+        assert_eq!(step(&mut cpu, &mut ram), 4, "{:02X}", instr);
+        assert_eq!(cpu.pc, START + 1, "{:02X}", instr);
+        assert_eq!(cpu.sp, STACK, "{:02X}", instr);
+    }
+}
+
+#[test]
+fn undocumented_jmp() {
+    let (mut cpu, mut ram) = setup(&[0xcb, 0x34, 0x12]);
+    assert_eq!(step(&mut cpu, &mut ram), 10);
+    assert_eq!(cpu.pc, 0x1234);
+    assert_eq!(cpu.sp, STACK);
+}
+
+#[test]
+fn undocumented_calls() {
+    for &instr in &[0xdd, 0xed, 0xfd] {
+        let (mut cpu, mut ram) = setup(&[instr, 0x34, 0x12]);
+        assert_eq!(step(&mut cpu, &mut ram), 17, "{:02X}", instr);
+        assert_eq!(cpu.pc, 0x1234, "{:02X}", instr);
+        assert_eq!(cpu.sp, STACK - 2, "{:02X}", instr);
+        assert_eq!(cpu.pop(&ram), START + 3, "{:02X}", instr);
+    }
+}
+
+#[test]
+fn undocumented_ret() {
+    let (mut cpu, mut ram) = setup(&[0xd9]);
+    cpu.push(&mut ram, 0x1234);
+    assert_eq!(step(&mut cpu, &mut ram), 10);
+    assert_eq!(cpu.pc, 0x1234);
+    assert_eq!(cpu.sp, STACK);
+}
+
+// POP PSW from a stack word holding `flags`, then back to START
+fn set_flags(cpu: &mut I8080, ram: &mut Ram, flags: u8) {
+    cpu.push(ram, flags as usize);
+    cpu.execute(ram, 0xf1);
+    cpu.pc = START;
+}
+
+// CALL and RET opcodes of every condition with the flag it tests and whether a set flag makes it branch
+const CONDITIONS: [(u8, u8, u8, bool); 8] = [
+    (0xc4, 0xc0, 1 << 6, false), // NZ
+    (0xcc, 0xc8, 1 << 6, true),  // Z
+    (0xd4, 0xd0, 1 << 0, false), // NC
+    (0xdc, 0xd8, 1 << 0, true),  // C
+    (0xe4, 0xe0, 1 << 2, false), // PO
+    (0xec, 0xe8, 1 << 2, true),  // PE
+    (0xf4, 0xf0, 1 << 7, false), // P
+    (0xfc, 0xf8, 1 << 7, true),  // M
+];
+
+#[test]
+fn conditional_calls_take_11_cycles_or_17_when_taken() {
+    for &(call, _, flag, taken_when_set) in &CONDITIONS {
+        for &set in &[false, true] {
+            let (mut cpu, mut ram) = setup(&[call, 0x34, 0x12]);
+            set_flags(&mut cpu, &mut ram, 0x02 | if set { flag } else { 0 });
+            let taken = set == taken_when_set;
+
+            assert_eq!(step(&mut cpu, &mut ram), if taken { 17 } else { 11 }, "{:02X} taken: {}", call, taken);
+            if taken {
+                assert_eq!(cpu.pc, 0x1234);
+                assert_eq!(cpu.pop(&ram), START + 3);
+            } else {
+                assert_eq!(cpu.pc, START + 3);
+                assert_eq!(cpu.sp, STACK);
+            }
+        }
+    }
+}
+
+#[test]
+fn conditional_returns_take_5_cycles_or_11_when_taken() {
+    for &(_, ret, flag, taken_when_set) in &CONDITIONS {
+        for &set in &[false, true] {
+            let (mut cpu, mut ram) = setup(&[ret]);
+            set_flags(&mut cpu, &mut ram, 0x02 | if set { flag } else { 0 });
+            cpu.push(&mut ram, 0x1234);
+            let taken = set == taken_when_set;
+
+            assert_eq!(step(&mut cpu, &mut ram), if taken { 11 } else { 5 }, "{:02X} taken: {}", ret, taken);
+            if taken {
+                assert_eq!(cpu.pc, 0x1234);
+                assert_eq!(cpu.sp, STACK);
+            } else {
+                assert_eq!(cpu.pc, START + 1);
+                assert_eq!(cpu.sp, STACK - 2);
+            }
+        }
+    }
+}
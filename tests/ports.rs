@@ -0,0 +1,18 @@
+use space_invaders_emulator::i8080::Bus;
+use space_invaders_emulator::machine::Machine;
+
+#[test]
+fn undecoded_ports_read_open_bus_and_ignore_writes() {
+    // MVI A,12; OUT 7; OUT 0; IN 7; HLT
+    let mut machine = Machine::new();
+    machine.load_rom(&[0x3e, 0x12, 0xd3, 0x07, 0xd3, 0x00, 0xdb, 0x07, 0x76]);
+    machine.execute_n_cycles(7 + 10 + 10 + 10);
+
+    assert_eq!(machine.cpu.a, 0xFF);
+    assert_eq!(machine.cpu.pc, 0x08);
+    assert!(machine.take_sound_writes().is_empty());
+
+    for port in 4..=255 {
+        assert_eq!(machine.bus.read_port(port), 0xFF);
+    }
+}
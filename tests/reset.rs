@@ -0,0 +1,42 @@
+use space_invaders_emulator::i8080::Bus;
+use space_invaders_emulator::machine::{Machine, ResetKind};
+use space_invaders_emulator::rom::{load_si_rom, DEFAULT_ROM_DIR};
+use std::path::Path;
+
+fn booted_machine(frames: usize) -> Machine {
+    let mut machine = Machine::new();
+    machine.load_rom(&load_si_rom(Path::new(DEFAULT_ROM_DIR)).unwrap());
+    for _ in 0..frames {
+        machine.run_frame(2_000_000, 60.0);
+    }
+    machine
+}
+
+#[test]
+fn hard_reset_behaves_like_power_on() {
+    let mut machine = booted_machine(200);
+    machine.bus.write_port(4, 0xAB);
+    machine.reset(ResetKind::Hard);
+    let fresh = booted_machine(0);
+
+    assert!(machine.bus.memory[..] == fresh.bus.memory[..]);
+    assert_eq!(machine.bus.read_port(3), 0);
+
+    for _ in 0..100 {
+        machine.run_frame(2_000_000, 60.0);
+    }
+    assert!(machine.bus.memory[..] == booted_machine(100).bus.memory[..]);
+}
+
+#[test]
+fn soft_reset_restarts_the_cpu_and_keeps_ram() {
+    let mut machine = booted_machine(200);
+    machine.bus.port3 = 0x01;
+    let ram = machine.bus.memory[0x2000..0x4000].to_vec();
+    machine.reset(ResetKind::Soft);
+
+    assert_eq!(machine.cpu.pc, 0);
+    assert!(!machine.cpu.enable_interrupts);
+    assert_eq!(machine.bus.port3, 0);
+    assert!(machine.bus.memory[0x2000..0x4000] == ram[..]);
+}
@@ -0,0 +1,25 @@
+use space_invaders_emulator::machine::Machine;
+use space_invaders_emulator::rom::{load_si_rom, DEFAULT_ROM_DIR};
+use space_invaders_emulator::rewind::RewindBuffer;
+use std::path::Path;
+
+#[test]
+fn rewinding_steps_back_one_captured_frame_at_a_time() {
+    let mut machine = Machine::new();
+    machine.load_rom(&load_si_rom(Path::new(DEFAULT_ROM_DIR)).unwrap());
+    let mut rewind = RewindBuffer::new(3, 1);
+
+    let mut frames = Vec::new();
+    for _ in 0..5 {
+        frames.push(machine.bus.memory.to_vec());
+        rewind.capture(&machine);
+        machine.run_frame(2_000_000, 60.0);
+    }
+    assert_eq!(rewind.len(), 3);
+
+    for expected in frames.iter().rev().take(3) {
+        assert!(rewind.rewind(&mut machine));
+        assert!(machine.bus.memory[..] == expected[..]);
+    }
+    assert!(!rewind.rewind(&mut machine));
+}
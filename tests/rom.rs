@@ -0,0 +1,60 @@
+use space_invaders_emulator::rom::{load_si_rom, ChipProblem, RomError, DEFAULT_ROM_DIR, INVADERS_CHIPS};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+
+fn chip_data(name: &str) -> Vec<u8> {
+    std::fs::read(Path::new(DEFAULT_ROM_DIR).join(name)).unwrap()
+}
+
+fn write_zip(test: &str, entries: &[(&str, Vec<u8>)]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("space_invaders_{}_{}.zip", test, std::process::id()));
+    let mut writer = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+    for (name, data) in entries {
+        writer.start_file(*name, SimpleFileOptions::default()).unwrap();
+        writer.write_all(data).unwrap();
+    }
+    writer.finish().unwrap();
+    path
+}
+
+#[test]
+fn zip_set_loads_the_same_rom_as_loose_files() {
+    let loose = load_si_rom(Path::new(DEFAULT_ROM_DIR)).unwrap();
+    assert_eq!(loose.len(), 0x2000);
+
+    // entry order and case should not matter
+    let entries = INVADERS_CHIPS.iter().rev()
+        .map(|chip| (chip.name, chip_data(chip.name)))
+        .map(|(name, data)| (if name == "invaders.g" { "INVADERS.G" } else { name }, data))
+        .collect::<Vec<_>>();
+    let path = write_zip("good", &entries);
+    let zipped = load_si_rom(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(zipped.unwrap() == loose);
+}
+
+#[test]
+fn missing_and_bad_chips_are_reported_by_name() {
+    let mut bad_f = chip_data("invaders.f");
+    bad_f[0x100] ^= 0xFF;
+    let path = write_zip("bad", &[
+        ("invaders.h", chip_data("invaders.h")),
+        ("invaders.f", bad_f),
+        ("invaders.e", chip_data("invaders.e")[..0x400].to_vec()),
+    ]);
+    let result = load_si_rom(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    match result {
+        Err(RomError::BadChips(problems)) => {
+            assert_eq!(problems.len(), 3);
+            assert_eq!(problems[0], ("invaders.g", ChipProblem::Missing));
+            assert_eq!(problems[1].0, "invaders.f");
+            assert!(matches!(problems[1].1, ChipProblem::WrongChecksum { .. }));
+            assert_eq!(problems[2], ("invaders.e", ChipProblem::WrongSize(0x400)));
+        }
+        _ => panic!("expected the bad chips to be reported"),
+    }
+}
@@ -0,0 +1,63 @@
+use space_invaders_emulator::machine::Machine;
+use space_invaders_emulator::rom::{load_si_rom, DEFAULT_ROM_DIR};
+use space_invaders_emulator::save_state::{self, StateError};
+use space_invaders_emulator::screen::ScreenDevice;
+use std::path::Path;
+
+#[test]
+fn loading_a_state_replays_the_same_frames() {
+    let mut machine = Machine::new();
+    machine.load_rom(&load_si_rom(Path::new(DEFAULT_ROM_DIR)).unwrap());
+    for _ in 0..100 {
+        machine.run_frame(2_000_000, 60.0);
+    }
+
+    let state = machine.save_state();
+    for _ in 0..50 {
+        machine.run_frame(2_000_000, 60.0);
+    }
+    let first_run = machine.bus.memory.to_vec();
+    let first_pc = machine.cpu.pc;
+
+    machine.load_state(&state);
+    for _ in 0..50 {
+        machine.run_frame(2_000_000, 60.0);
+    }
+
+    assert_eq!(machine.cpu.pc, first_pc);
+    assert!(machine.bus.memory[..] == first_run[..]);
+}
+
+#[test]
+fn state_file_round_trips_and_rejects_other_rom_sets() {
+    let mut machine = Machine::new();
+    machine.load_rom(&load_si_rom(Path::new(DEFAULT_ROM_DIR)).unwrap());
+    for _ in 0..100 {
+        machine.run_frame(2_000_000, 60.0);
+    }
+
+    let path = std::env::temp_dir().join(format!("si_state_test_{}.sav", std::process::id()));
+    let thumbnail = ScreenDevice::new().thumbnail(&machine.bus.memory);
+    save_state::save_to_file(&path, &machine.save_state(), machine.rom_crc, &thumbnail).unwrap();
+
+    let (header, state) = save_state::load_from_file(&path, machine.rom_crc).unwrap();
+    assert_eq!(header.version, save_state::FORMAT_VERSION);
+    assert_eq!(header.thumbnail, thumbnail);
+    assert_eq!(state.cpu.pc, machine.cpu.pc);
+    assert_eq!(state.ram[..], machine.bus.memory[0x2000..0x4000]);
+
+    let other_rom = save_state::load_from_file(&path, machine.rom_crc ^ 1);
+    assert!(matches!(other_rom, Err(StateError::RomMismatch { .. })));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn wrong_thumbnail_size_is_an_error_and_writes_nothing() {
+    let machine = Machine::new();
+    let path = std::env::temp_dir().join(format!("si_state_thumbnail_test_{}.sav", std::process::id()));
+
+    let err = save_state::save_to_file(&path, &machine.save_state(), machine.rom_crc, &[0; 10]).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(!path.exists());
+}
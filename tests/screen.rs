@@ -0,0 +1,23 @@
+use space_invaders_emulator::screen::{ScreenDevice, SCREEN_WIDTH, SCREEN_HEIGHT};
+
+fn lit_pixels(frame: &[u8]) -> Vec<(usize, usize)> {
+    frame.chunks(3).enumerate()
+        .filter(|(_, pixel)| pixel.iter().any(|&channel| channel != 0))
+        .map(|(i, _)| (i % SCREEN_WIDTH, i / SCREEN_WIDTH))
+        .collect()
+}
+
+#[test]
+fn cocktail_flip_turns_the_picture_around() {
+    let mut memory = [0; 0x10000];
+    memory[0x2400 + 32 * 10 + 3] = 0b0000_0100; // column 10, bit 26 of the scanline
+
+    let mut screen = ScreenDevice::new();
+    let upright = lit_pixels(&screen.render(&memory));
+    screen.flipped = true;
+    let flipped = lit_pixels(&screen.render(&memory));
+
+    assert_eq!(upright.len(), 1);
+    let (x, y) = upright[0];
+    assert_eq!(flipped, vec![(SCREEN_WIDTH - 1 - x, SCREEN_HEIGHT - 1 - y)]);
+}
@@ -0,0 +1,71 @@
+use space_invaders_emulator::sound::{load_sounds, Sound, SoundEvent, SoundLatches, SoundManifest, MANIFEST_NAME};
+use std::path::Path;
+
+#[test]
+fn shipped_manifest_maps_every_sound_to_its_sample() {
+    let manifest = SoundManifest::load(Path::new("./sounds")).unwrap();
+    for sound in &Sound::ALL {
+        assert_eq!(manifest.path(*sound), Path::new("./sounds").join(sound.default_file()));
+        assert!(manifest.path(*sound).exists(), "{} has no sample", sound.name());
+    }
+}
+
+#[test]
+fn manifest_overrides_defaults_and_rejects_unknown_sounds() {
+    let dir = std::env::temp_dir().join(format!("space_invaders_sounds_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    std::fs::write(dir.join(MANIFEST_NAME), "ufo = \"saucer.wav\"\n").unwrap();
+    let manifest = SoundManifest::load(&dir).unwrap();
+    assert_eq!(manifest.path(Sound::Ufo), dir.join("saucer.wav"));
+    assert_eq!(manifest.path(Sound::Shot), dir.join("01.wav"));
+
+    std::fs::write(dir.join(MANIFEST_NAME), "saucer = \"saucer.wav\"\n").unwrap();
+    assert!(SoundManifest::load(&dir).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn latches_start_sounds_on_rising_edges_only() {
+    let mut latches = SoundLatches::new();
+    // shot and invader death together, both start
+    assert_eq!(latches.write(3, 0b1010), vec![SoundEvent::Start(Sound::Shot), SoundEvent::Start(Sound::InvaderDie)]);
+    // still set, nothing new
+    assert_eq!(latches.write(3, 0b1010), vec![]);
+    // one-shots are not cut off when their bit clears
+    assert_eq!(latches.write(3, 0b0000), vec![]);
+    assert_eq!(latches.write(5, 0b0001_0001), vec![SoundEvent::Start(Sound::Fleet1), SoundEvent::Start(Sound::UfoHit)]);
+}
+
+#[test]
+fn ufo_siren_loops_while_its_bit_is_set() {
+    let mut latches = SoundLatches::new();
+    assert!(Sound::Ufo.looping());
+    assert_eq!(latches.write(3, 0b01), vec![SoundEvent::Start(Sound::Ufo)]);
+    assert_eq!(latches.write(3, 0b11), vec![SoundEvent::Start(Sound::Shot)]);
+    assert_eq!(latches.write(3, 0b10), vec![SoundEvent::Stop(Sound::Ufo)]);
+
+    // restoring a state with the saucer flying resumes only the siren
+    assert_eq!(latches.restore(0b1001, 0b0001), vec![SoundEvent::Amplifier(false), SoundEvent::Start(Sound::Ufo)]);
+    assert_eq!(latches.write(3, 0b1000), vec![SoundEvent::Stop(Sound::Ufo)]);
+}
+
+#[test]
+fn bit_5_of_port_3_switches_the_amplifier() {
+    let mut latches = SoundLatches::new();
+    assert!(!latches.amplifier_enabled());
+    assert_eq!(latches.write(3, 0b10_0000), vec![SoundEvent::Amplifier(true)]);
+    assert_eq!(latches.write(3, 0b10_0010), vec![SoundEvent::Start(Sound::Shot)]);
+    assert_eq!(latches.write(3, 0b00_0000), vec![SoundEvent::Amplifier(false)]);
+    // port 5 bit 5 is the screen flip, not a sound
+    assert_eq!(latches.write(5, 0b10_0000), vec![]);
+}
+
+#[test]
+fn shipped_samples_load_without_falling_back_to_the_synthesizer() {
+    let (sounds, problems) = load_sounds(Path::new("./sounds"), 44_100);
+    assert!(problems.is_empty(), "{:?}", problems);
+    assert_eq!(sounds.len(), Sound::ALL.len());
+    assert!(sounds.iter().all(|samples| !samples.is_empty()));
+}
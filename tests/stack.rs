@@ -0,0 +1,42 @@
+use space_invaders_emulator::i8080::{Bus, I8080, Ram};
+
+#[test]
+fn stack_wraps_around_the_address_space() {
+    let mut cpu = I8080::new();
+    let mut ram = Ram::new();
+
+    cpu.sp = 0x0001;
+    cpu.push(&mut ram, 0x1234);
+    assert_eq!(cpu.sp, 0xFFFF);
+    assert_eq!(ram.read_memory(0xFFFF), 0x34);
+    assert_eq!(ram.read_memory(0x0000), 0x12);
+    assert_eq!(cpu.pop(&ram), 0x1234);
+    assert_eq!(cpu.sp, 0x0001);
+
+    cpu.sp = 0x0000;
+    cpu.push(&mut ram, 0xABCD);
+    assert_eq!(cpu.sp, 0xFFFE);
+    assert_eq!(cpu.pop(&ram), 0xABCD);
+    assert_eq!(cpu.sp, 0x0000);
+}
+
+#[test]
+fn call_and_xthl_at_the_top_of_memory_wrap() {
+    let mut cpu = I8080::new();
+    let mut ram = Ram::new();
+    // CALL 0200; at 0200 XTHL
+    ram.load(&[0xcd, 0x00, 0x02], 0x100);
+    ram.load(&[0xe3], 0x200);
+    cpu.pc = 0x100;
+    cpu.sp = 0x0001;
+    cpu.h = 0x56;
+    cpu.l = 0x78;
+
+    for _ in 0..2 {
+        let instr = cpu.read_instr(&ram).unwrap();
+        cpu.execute(&mut ram, instr);
+    }
+    assert_eq!(cpu.sp, 0xFFFF);
+    assert_eq!((cpu.h, cpu.l), (0x01, 0x03));
+    assert_eq!(cpu.pop(&ram), 0x5678);
+}
@@ -0,0 +1,19 @@
+use space_invaders_emulator::sound::Sound;
+use space_invaders_emulator::synth::synthesize;
+
+#[test]
+fn every_sound_is_audible_and_short() {
+    for &sound in &Sound::ALL {
+        let samples = synthesize(sound, 44_100);
+        assert!(!samples.is_empty() && samples.len() <= 2 * 44_100, "{} has {} samples", sound.name(), samples.len());
+        let peak = samples.iter().map(|sample| sample.unsigned_abs()).max().unwrap();
+        assert!(peak > i16::MAX as u16 / 8, "{} is too quiet", sound.name());
+    }
+}
+
+#[test]
+fn rendering_is_reproducible() {
+    for &sound in &Sound::ALL {
+        assert!(synthesize(sound, 22_050) == synthesize(sound, 22_050));
+    }
+}
@@ -0,0 +1,32 @@
+use space_invaders_emulator::wav::{Wav, WavWriter};
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{}-{}.wav", name, std::process::id()))
+}
+
+#[test]
+fn captured_audio_reads_back() {
+    let path = temp_path("capture");
+    let mut writer = WavWriter::create(&path, 22_050).unwrap();
+    writer.write(&[0, 1, -1, i16::MAX]).unwrap();
+    writer.write(&[i16::MIN, 42]).unwrap();
+
+    // readable before the writer is dropped, the header is kept up to date
+    let wav = Wav::read(&path).unwrap();
+    assert_eq!(wav.sample_rate, 22_050);
+    assert_eq!(wav.samples, vec![0, 1, -1, i16::MAX, i16::MIN, 42]);
+
+    drop(writer);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn empty_capture_is_a_valid_file() {
+    let path = temp_path("empty");
+    drop(WavWriter::create(&path, 44_100).unwrap());
+
+    let wav = Wav::read(&path).unwrap();
+    assert!(wav.samples.is_empty());
+    std::fs::remove_file(&path).unwrap();
+}